@@ -0,0 +1,188 @@
+use std::fmt;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::thread;
+use std::time::Duration;
+
+use serde::Serialize;
+
+use crate::cube::Moves;
+use crate::error::Error;
+use crate::report::{self, SolveReport};
+
+/*
+   batch solving: run() hands job indices 0..count to a fixed set of worker threads, which take
+   the next index whenever they finish one, and returns the results in index order.
+   jobs share whatever they capture, typically one SolverContext, so tables are loaded once.
+   BatchStats summarizes the reports of a batch, BatchLine is the result of one line of a batch file.
+*/
+
+pub fn run<T, F>(count: usize, threads: usize, job: F) -> Vec<T>
+where
+    T: Send,
+    F: Fn(usize) -> T + Sync,
+{
+    let next = AtomicUsize::new(0);
+    let mut results: Vec<(usize, T)> = thread::scope(|s| {
+        let workers: Vec<_> = (0..threads.max(1)).map(|_| s.spawn(|| {
+            let mut results = vec![];
+            loop {
+                let i = next.fetch_add(1, Ordering::Relaxed);
+                if i >= count {
+                    return results;
+                }
+                results.push((i, job(i)));
+            }
+        })).collect();
+        workers.into_iter().flat_map(|worker| worker.join().unwrap()).collect()
+    });
+    results.sort_by_key(|(i, _)| *i);
+    results.into_iter().map(|(_, result)| result).collect()
+}
+
+// mean, median, 95th percentile and max of a sample, by nearest rank
+#[derive(Copy, Clone, Debug, Default)]
+pub struct Summary<T> {
+    pub mean: f64,
+    pub median: T,
+    pub p95: T,
+    pub max: T,
+}
+
+impl<T: Copy + Default + Ord> Summary<T> {
+    fn new(mut values: Vec<T>, to_f64: fn(T) -> f64) -> Self {
+        if values.is_empty() {
+            return Self::default();
+        }
+        values.sort();
+        let rank = |p: f64| values[((p * values.len() as f64).ceil() as usize).clamp(1, values.len()) - 1];
+        Self {
+            mean: values.iter().map(|v| to_f64(*v)).sum::<f64>() / values.len() as f64,
+            median: rank(0.5),
+            p95: rank(0.95),
+            max: values[values.len() - 1],
+        }
+    }
+}
+
+#[derive(Clone, Debug)]
+pub struct BatchStats {
+    pub solves: usize,
+    pub successes: usize,
+    pub length: Summary<usize>, // HTM, successful solves only
+    pub time: Summary<Duration>,
+    pub phase_lengths: Vec<(String, Vec<usize>)>, // phase name, solves per phase length, successful phases only
+}
+
+impl BatchStats {
+    pub fn new(reports: &[SolveReport]) -> Self {
+        let successful = || reports.iter().filter(|r| r.success);
+        let mut phase_lengths: Vec<(String, Vec<usize>)> = vec![];
+        for phase in reports.iter().flat_map(|r| &r.phases).filter(|p| p.success) {
+            let i = match phase_lengths.iter().position(|(name, _)| *name == phase.name) {
+                Some(i) => i,
+                None => {
+                    phase_lengths.push((phase.name.clone(), vec![]));
+                    phase_lengths.len() - 1
+                }
+            };
+            let histogram = &mut phase_lengths[i].1;
+            let length = phase.moves.htm_length();
+            if histogram.len() <= length {
+                histogram.resize(length + 1, 0);
+            }
+            histogram[length] += 1;
+        }
+        Self {
+            solves: reports.len(),
+            successes: successful().count(),
+            length: Summary::new(successful().map(|r| r.length_htm).collect(), |v| v as f64),
+            time: Summary::new(reports.iter().map(|r| r.duration).collect(), |v| v.as_secs_f64()),
+            phase_lengths,
+        }
+    }
+
+    pub fn success_rate(&self) -> f64 {
+        if self.solves == 0 { 0.0 } else { self.successes as f64 / self.solves as f64 }
+    }
+}
+
+impl fmt::Display for BatchStats {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        const BAR_WIDTH: usize = 40;
+        writeln!(f, "Solves: {}, success rate: {:.2}%", self.solves, self.success_rate() * 100.0)?;
+        writeln!(f, "Length (HTM): mean {:.2}, median {}, p95 {}, max {}",
+            self.length.mean, self.length.median, self.length.p95, self.length.max)?;
+        write!(f, "Time: mean {:.3?}, median {:.3?}, p95 {:.3?}, max {:.3?}",
+            Duration::from_secs_f64(self.time.mean), self.time.median, self.time.p95, self.time.max)?;
+        for (name, histogram) in &self.phase_lengths {
+            write!(f, "\n{} lengths:", name)?;
+            let most = histogram.iter().copied().max().unwrap_or(0).max(1);
+            for (length, &count) in histogram.iter().enumerate().filter(|(_, count)| **count > 0) {
+                write!(f, "\n{:>4} | {:<width$} {}", length, "#".repeat((count * BAR_WIDTH).div_ceil(most)), count, width = BAR_WIDTH)?;
+            }
+        }
+        Ok(())
+    }
+}
+
+#[derive(Clone, Debug, Serialize)]
+pub struct BatchLine {
+    pub line: usize, // 1-based line number in the input
+    pub input: String,
+    pub success: bool,
+    pub solution: Option<Moves>,
+    pub length_htm: Option<usize>,
+    #[serde(rename = "duration_ms", serialize_with = "report::serialize_millis")]
+    pub duration: Duration,
+    pub error: Option<String>,
+}
+
+impl BatchLine {
+    pub const CSV_HEADER: &str = "line,input,success,solution,length_htm,duration_ms,error";
+
+    // result is the solve of the line's cube, or why the line could not be solved
+    pub fn new(line: usize, input: &str, result: Result<SolveReport, Error>) -> Self {
+        let mut batch_line = Self {
+            line,
+            input: input.to_string(),
+            success: false,
+            solution: None,
+            length_htm: None,
+            duration: Duration::ZERO,
+            error: None,
+        };
+        match result {
+            Ok(report) => {
+                batch_line.success = true;
+                batch_line.length_htm = Some(report.length_htm);
+                batch_line.solution = Some(report.solution);
+                batch_line.duration = report.duration;
+            }
+            Err(e) => {
+                batch_line.duration = e.report().map_or(Duration::ZERO, |report| report.duration);
+                batch_line.error = Some(e.to_string());
+            }
+        }
+        batch_line
+    }
+
+    pub fn to_csv(&self) -> String {
+        // quoted when needed, with quotes doubled
+        fn field(s: &str) -> String {
+            if s.contains([',', '"', '\n', '\r']) {
+                format!("\"{}\"", s.replace('"', "\"\""))
+            } else {
+                s.to_string()
+            }
+        }
+        [
+            self.line.to_string(),
+            field(&self.input),
+            self.success.to_string(),
+            self.solution.as_ref().map_or(String::new(), |moves| moves.to_string()),
+            self.length_htm.map_or(String::new(), |length| length.to_string()),
+            format!("{:.3}", self.duration.as_secs_f64() * 1000.0),
+            field(self.error.as_deref().unwrap_or("")),
+        ].join(",")
+    }
+}
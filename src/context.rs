@@ -0,0 +1,140 @@
+use std::path::{Path, PathBuf};
+use std::sync::{Mutex, OnceLock};
+use std::time::Duration;
+
+use crate::cube::Cube;
+use crate::error::Error;
+use crate::kociemba::KociembaTables;
+use crate::optimal::OptimalTables;
+use crate::prune_table::PruneTable;
+use crate::report::SolveReport;
+use crate::solver::ThistlethwaiteTables;
+
+#[derive(Copy, Clone, Debug, Eq, PartialEq, Hash)]
+pub enum SolverKind {
+    Thistlethwaite, // four phase, fast, long solutions
+    Kociemba,       // two phase, searches for shorter solutions within a time budget
+    Optimal,        // shortest solutions, practical for scrambles up to about 14 moves
+}
+
+#[derive(Copy, Clone, Debug)]
+pub struct SolveOptions {
+    pub solver: SolverKind,
+    pub target_length: usize, // Kociemba: stop once a solution is at most this long
+    pub time_budget: Duration, // Kociemba: time to keep looking for shorter solutions
+    pub print_moves: bool, // print each phase and the solution while solving
+}
+
+impl Default for SolveOptions {
+    fn default() -> Self {
+        Self {
+            solver: SolverKind::Thistlethwaite,
+            target_length: 20,
+            time_budget: Duration::from_millis(500),
+            print_moves: false,
+        }
+    }
+}
+
+/*
+   owns the prune tables of every solver so they are loaded once and reused across solves.
+   each solver's tables are loaded from the table directory on its first solve, generating the
+   missing ones and regenerating corrupt or outdated ones, and again on the next solve if that
+   failed. the context is Send + Sync, so threads can share one by reference or through an Arc.
+*/
+pub struct SolverContext {
+    table_dir: PathBuf,
+    load_lock: Mutex<()>, // one thread loads, or generates, the tables the others wait for
+    thistlethwaite: OnceLock<ThistlethwaiteTables>,
+    kociemba: OnceLock<KociembaTables>,
+    optimal: OnceLock<OptimalTables>,
+}
+
+impl Default for SolverContext {
+    fn default() -> Self {
+        Self::with_table_dir(PruneTable::default_dir())
+    }
+}
+
+const _: () = {
+    const fn assert_send_sync<T: Send + Sync>() {}
+    assert_send_sync::<SolverContext>();
+};
+
+impl SolverContext {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn with_table_dir(table_dir: impl Into<PathBuf>) -> Self {
+        Self {
+            table_dir: table_dir.into(),
+            load_lock: Mutex::new(()),
+            thistlethwaite: OnceLock::new(),
+            kociemba: OnceLock::new(),
+            optimal: OnceLock::new(),
+        }
+    }
+
+    pub fn table_dir(&self) -> &Path {
+        &self.table_dir
+    }
+
+    fn get_or_load<'a, T>(&self, tables: &'a OnceLock<T>, load: fn(&Path) -> Result<T, Error>) -> Result<&'a T, Error> {
+        if let Some(tables) = tables.get() {
+            return Ok(tables);
+        }
+        let _guard = self.load_lock.lock().unwrap_or_else(|poisoned| poisoned.into_inner());
+        if let Some(tables) = tables.get() { // loaded while waiting for the lock
+            return Ok(tables);
+        }
+        let loaded = load(&self.table_dir)?;
+        Ok(tables.get_or_init(|| loaded))
+    }
+
+    pub(crate) fn thistlethwaite_tables(&self) -> Result<&ThistlethwaiteTables, Error> {
+        self.get_or_load(&self.thistlethwaite, ThistlethwaiteTables::load)
+    }
+
+    pub(crate) fn kociemba_tables(&self) -> Result<&KociembaTables, Error> {
+        self.get_or_load(&self.kociemba, KociembaTables::load)
+    }
+
+    pub(crate) fn optimal_tables(&self) -> Result<&OptimalTables, Error> {
+        self.get_or_load(&self.optimal, OptimalTables::load)
+    }
+
+    // loads the tables a solver needs ahead of its first solve, e.g. to keep that out of timings
+    pub fn preload(&self, solver: SolverKind) -> Result<(), Error> {
+        match solver {
+            SolverKind::Thistlethwaite => self.thistlethwaite_tables().map(|_| ()),
+            SolverKind::Kociemba => self.kociemba_tables().map(|_| ()),
+            SolverKind::Optimal => self.optimal_tables().and(self.kociemba_tables()).map(|_| ()),
+        }
+    }
+
+    /*
+       the solvers search the cube with standard centers, so each move turns the face it is named after.
+       this checks the cube and calls solve with it as given, for printing, and with its centers reset,
+       for searching; the moves of the report, or of the report of a failed search, are then restated
+       for the cube as given
+    */
+    pub(crate) fn solve_reframed(cube: Cube, solve: impl FnOnce(Cube, Cube) -> Result<SolveReport, Error>) -> Result<SolveReport, Error> {
+        cube.validate()?;
+        match solve(cube, cube.reset_centers()) {
+            Ok(report) => Ok(report.reframe(cube)),
+            Err(Error::SearchExhausted(report)) => Err(Error::SearchExhausted(Box::new(report.reframe(cube)))),
+            Err(Error::Timeout(report)) => Err(Error::Timeout(Box::new(report.reframe(cube)))),
+            Err(e) => Err(e),
+        }
+    }
+
+    pub fn solve(&self, cube: Cube, options: &SolveOptions) -> Result<SolveReport, Error> {
+        let name = "solve".to_string();
+        match options.solver {
+            SolverKind::Thistlethwaite => self.solve_thistlethwaite(cube, name, options.print_moves),
+            SolverKind::Kociemba => self.solve_kociemba(cube, name, options.print_moves, options.target_length, options.time_budget),
+            SolverKind::Optimal => self.solve_optimal(cube, name, options.print_moves),
+        }
+    }
+}
@@ -0,0 +1,253 @@
+use std::collections::VecDeque;
+
+use crate::cube::{Cube, Mov};
+use crate::prune_table;
+use crate::solver::Solver;
+
+/*
+   coordinates and their move tables.
+   a coordinate is an integer describing one aspect of the cube, e.g. its corner orientations,
+   that a move changes independently of everything else. a move table maps coordinate × move
+   to the coordinate after the move, so searches can follow moves on integers alone instead of
+   applying them to a Cube and recomputing indices.
+   a phase combines several coordinates into one index, least significant first, the same
+   index its prune table is generated with. its definition, the moves and coordinates, is
+   hashed into the prune table header so a table generated for another definition is refused.
+*/
+
+// edges outside the LR slice, permuted among themselves in the domino subgroup
+const NON_LR_SLICE_EDGES: [u8; 8] = [1, 3, 4, 5, 6, 7, 9, 11];
+
+/*
+   flip_bits is nonzero for coordinates of where some edges are and how they are flipped, which have
+   too many values for a plain move table: the value is positions << flip_bits | flips, with one
+   flip bit per edge. a move table follows the positions, and a flip table which edges the move flips,
+   which depends on where the edges are only.
+*/
+#[derive(Copy, Clone)]
+pub(crate) struct Coordinate {
+    pub get: fn(Cube) -> u32,
+    pub size: u32, // number of values the coordinate can take
+    pub flip_bits: u32,
+}
+
+// the last corner's orientation follows from the others
+pub fn corner_orientation(cube: Cube) -> u32 {
+    Solver::orientations_to_index(&cube.corner_orientations[..7], 3)
+}
+
+pub fn edge_orientation(cube: Cube) -> u32 {
+    Solver::orientations_to_index(&cube.edge_orientations[..11], 2)
+}
+
+pub fn lr_slice_combination(cube: Cube) -> u32 {
+    Solver::get_cubies_position_index(&cube.edge_permutations, &Cube::LR_SLICE_EDGES)
+}
+
+pub fn ud_slice_combination(cube: Cube) -> u32 {
+    Solver::get_cubies_position_index(&cube.edge_permutations, &Cube::UD_SLICE_EDGES)
+}
+
+pub fn half_tetrad_1_combination(cube: Cube) -> u32 {
+    Solver::get_cubies_position_index(&cube.corner_permutations, &Cube::HALF_TETRAD_1_CORNERS)
+}
+
+pub fn half_tetrad_2_combination(cube: Cube) -> u32 {
+    Solver::get_cubies_position_index(&cube.corner_permutations, &Cube::HALF_TETRAD_2_CORNERS)
+}
+
+pub fn half_tetrad_3_combination(cube: Cube) -> u32 {
+    Solver::get_cubies_position_index(&cube.corner_permutations, &Cube::HALF_TETRAD_3_CORNERS)
+}
+
+// the permutation coordinates below only follow moves that keep their cubies on their own positions
+
+pub fn lr_slice_permutation(cube: Cube) -> u32 {
+    Solver::get_relabeled_permutation_index(&cube.edge_permutations, &Cube::LR_SLICE_EDGES)
+}
+
+pub fn ud_slice_permutation(cube: Cube) -> u32 {
+    Solver::get_relabeled_permutation_index(&cube.edge_permutations, &Cube::UD_SLICE_EDGES)
+}
+
+pub fn fb_slice_permutation(cube: Cube) -> u32 {
+    Solver::get_relabeled_permutation_index(&cube.edge_permutations, &Cube::FB_SLICE_EDGES)
+}
+
+pub fn tetrad_1_permutation(cube: Cube) -> u32 {
+    Solver::get_relabeled_permutation_index(&cube.corner_permutations, &Cube::TETRAD_1_CORNERS)
+}
+
+pub fn tetrad_2_permutation(cube: Cube) -> u32 {
+    Solver::get_relabeled_permutation_index(&cube.corner_permutations, &Cube::TETRAD_2_CORNERS)
+}
+
+pub fn corner_permutation(cube: Cube) -> u32 {
+    Solver::permutations_to_index(&cube.corner_permutations, 8)
+}
+
+pub fn non_lr_slice_edge_permutation(cube: Cube) -> u32 {
+    Solver::get_relabeled_permutation_index(&cube.edge_permutations, &NON_LR_SLICE_EDGES)
+}
+
+pub const CORNER_ORIENTATION: Coordinate = Coordinate { get: corner_orientation, size: 2187, flip_bits: 0 }; // 3^7
+pub const EDGE_ORIENTATION: Coordinate = Coordinate { get: edge_orientation, size: 2048, flip_bits: 0 }; // 2^11
+pub const LR_SLICE_COMBINATION: Coordinate = Coordinate { get: lr_slice_combination, size: 495, flip_bits: 0 }; // comb(12, 4)
+pub const UD_SLICE_COMBINATION: Coordinate = Coordinate { get: ud_slice_combination, size: 495, flip_bits: 0 };
+pub const HALF_TETRAD_1_COMBINATION: Coordinate = Coordinate { get: half_tetrad_1_combination, size: 28, flip_bits: 0 }; // comb(8, 2)
+pub const HALF_TETRAD_2_COMBINATION: Coordinate = Coordinate { get: half_tetrad_2_combination, size: 28, flip_bits: 0 };
+pub const HALF_TETRAD_3_COMBINATION: Coordinate = Coordinate { get: half_tetrad_3_combination, size: 28, flip_bits: 0 };
+pub const LR_SLICE_PERMUTATION: Coordinate = Coordinate { get: lr_slice_permutation, size: 24, flip_bits: 0 }; // perm(4, 4)
+pub const UD_SLICE_PERMUTATION: Coordinate = Coordinate { get: ud_slice_permutation, size: 24, flip_bits: 0 };
+pub const FB_SLICE_PERMUTATION: Coordinate = Coordinate { get: fb_slice_permutation, size: 24, flip_bits: 0 };
+pub const TETRAD_1_PERMUTATION: Coordinate = Coordinate { get: tetrad_1_permutation, size: 24, flip_bits: 0 };
+pub const TETRAD_2_PERMUTATION: Coordinate = Coordinate { get: tetrad_2_permutation, size: 24, flip_bits: 0 };
+pub const CORNER_PERMUTATION: Coordinate = Coordinate { get: corner_permutation, size: 40320, flip_bits: 0 }; // 8!
+pub const NON_LR_SLICE_EDGE_PERMUTATION: Coordinate = Coordinate { get: non_lr_slice_edge_permutation, size: 40320, flip_bits: 0 };
+
+const UNREACHED: u32 = u32::MAX;
+
+pub(crate) struct MoveTable {
+    coordinate: Coordinate,
+    move_cnt: usize,
+    table: Vec<u32>, // coordinate * move_cnt + move index -> coordinate after the move, positions only with flip bits
+    flips: Vec<u8>, // positions * move_cnt + move index -> flip bits the move toggles, with flip bits only
+}
+
+impl MoveTable {
+    // BFS from solved, taking the first cube found with each coordinate value as its representative
+    pub fn new(coordinate: Coordinate, moves: &[Mov]) -> Self {
+        assert!(coordinate.flip_bits <= u8::BITS, "at most {} flip bits", u8::BITS);
+        let shift = coordinate.flip_bits;
+        let position_size = (coordinate.size >> shift) as usize;
+        let move_cnt = moves.len();
+        let mut table = vec![UNREACHED; position_size * move_cnt];
+        let mut flips = vec![0; if shift > 0 { position_size * move_cnt } else { 0 }];
+        let solved = Cube::new();
+        let mut visited = vec![false; position_size];
+        visited[((coordinate.get)(solved) >> shift) as usize] = true;
+        let mut queue = VecDeque::from([solved]);
+
+        while let Some(cube) = queue.pop_front() {
+            let coord = (coordinate.get)(cube);
+            let position = (coord >> shift) as usize;
+            for (i, m) in moves.iter().enumerate() {
+                let next_cube = cube.apply_move(*m);
+                let next_coord = (coordinate.get)(next_cube);
+                table[position * move_cnt + i] = next_coord >> shift;
+                if shift > 0 {
+                    flips[position * move_cnt + i] = ((coord ^ next_coord) & ((1 << shift) - 1)) as u8;
+                }
+                if !visited[(next_coord >> shift) as usize] {
+                    visited[(next_coord >> shift) as usize] = true;
+                    queue.push_back(next_cube);
+                }
+            }
+        }
+        Self { coordinate, move_cnt, table, flips }
+    }
+
+    // false for values no cube reachable with the table's moves has
+    pub fn is_reached(&self, coord: u32) -> bool {
+        coord < self.coordinate.size && self.table[(coord >> self.coordinate.flip_bits) as usize * self.move_cnt] != UNREACHED
+    }
+
+    pub fn apply(&self, coord: u32, move_index: usize) -> u32 {
+        let shift = self.coordinate.flip_bits;
+        let i = (coord >> shift) as usize * self.move_cnt + move_index;
+        if shift == 0 {
+            return self.table[i];
+        }
+        (self.table[i] << shift) | ((coord & ((1 << shift) - 1)) ^ self.flips[i] as u32)
+    }
+}
+
+#[derive(Clone)]
+pub(crate) struct PhaseDefinition {
+    pub moves: Vec<Mov>,
+    pub coordinates: Vec<Coordinate>, // least significant first
+}
+
+impl PhaseDefinition {
+    pub fn new(moves: &[Mov], coordinates: &[Coordinate]) -> Self {
+        Self { moves: moves.to_vec(), coordinates: coordinates.to_vec() }
+    }
+
+    pub fn build(&self) -> Phase {
+        Phase::new(&self.moves, &self.coordinates)
+    }
+
+    /*
+       hash of the moves, the coordinate sizes and the coordinates of a fixed walk of cubes,
+       so it changes with the move set or with what any coordinate computes
+    */
+    pub fn hash(&self) -> u64 {
+        const SAMPLE_CNT: usize = 256;
+        let mut bytes = vec![];
+        for m in &self.moves {
+            bytes.extend_from_slice(&[m.face as u8, m.dir as u8]);
+        }
+        for c in &self.coordinates {
+            bytes.extend_from_slice(&c.size.to_le_bytes());
+            bytes.extend_from_slice(&c.flip_bits.to_le_bytes());
+        }
+        let mut cube = Cube::new();
+        let mut state = 1u64;
+        for _ in 0..SAMPLE_CNT {
+            for c in &self.coordinates {
+                bytes.extend_from_slice(&(c.get)(cube).to_le_bytes());
+            }
+            state = state.wrapping_mul(6364136223846793005).wrapping_add(1442695040888963407);
+            cube = cube.apply_move(self.moves[(state >> 33) as usize % self.moves.len()]);
+        }
+        prune_table::hash64(&bytes)
+    }
+}
+
+pub(crate) struct Phase {
+    pub moves: Vec<Mov>,
+    move_tables: Vec<MoveTable>, // least significant first
+    pub size: u32, // number of values the phase index can take
+    pub solved_index: u32,
+    pub definition_hash: u64,
+}
+
+impl Phase {
+    pub fn new(moves: &[Mov], coordinates: &[Coordinate]) -> Self {
+        let mut phase = Self {
+            moves: moves.to_vec(),
+            move_tables: coordinates.iter().map(|c| MoveTable::new(*c, moves)).collect(),
+            size: coordinates.iter().map(|c| c.size).product(),
+            solved_index: 0,
+            definition_hash: PhaseDefinition::new(moves, coordinates).hash(),
+        };
+        phase.solved_index = phase.get_index(Cube::new()).unwrap_or(0);
+        phase
+    }
+
+    // None when the cube can't be reached with the phase's moves from any state its coordinates describe
+    pub fn get_index(&self, cube: Cube) -> Option<u32> {
+        let mut index = 0;
+        for table in self.move_tables.iter().rev() {
+            let coord = (table.coordinate.get)(cube);
+            if !table.is_reached(coord) {
+                return None;
+            }
+            index = index * table.coordinate.size + coord;
+        }
+        Some(index)
+    }
+
+    pub fn apply(&self, index: u32, move_index: usize) -> u32 {
+        let mut rest = index;
+        let mut multiplier = 1;
+        let mut next_index = 0;
+        for table in &self.move_tables {
+            let coord = rest % table.coordinate.size;
+            rest /= table.coordinate.size;
+            next_index += table.apply(coord, move_index) * multiplier;
+            multiplier *= table.coordinate.size;
+        }
+        next_index
+    }
+}
@@ -1,7 +1,10 @@
-use rand::{RngExt};
+use rand::seq::SliceRandom;
+use rand::{Rng, RngExt};
 use std::fmt;
 use std::vec::Vec;
 
+use crate::notation::ParseError;
+
 // corners
 // 0  URF    1  UFL
 // 2  ULB    3  UBR
@@ -19,9 +22,60 @@ pub type CornerPermutations = [u8; 8];
 pub type EdgeOrientations = [u8; 12]; // edge orientation flips on U/D moves
 pub type EdgePermutations = [u8; 12];
 
+pub type Centers = [Face; 6]; // face of the corner/edge frame now at each of the U, D, L, R, F, B positions
+
 #[derive(Copy, Clone, Eq, PartialEq, Hash, Debug)]
 pub enum Face {
-    U, D, L, R, F, B
+    U, D, L, R, F, B,
+    M, E, S,                // slices, following L, D and F
+    Uw, Dw, Lw, Rw, Fw, Bw, // wide turns, outer face and adjacent slice
+    X, Y, Z,                // whole cube rotations, following R, U and F
+}
+
+impl Face {
+    pub fn is_outer(self) -> bool {
+        matches!(self, Face::U | Face::D | Face::L | Face::R | Face::F | Face::B)
+    }
+
+    // moves about the same axis commute
+    pub fn axis(self) -> u8 {
+        match self {
+            Face::U | Face::D | Face::E | Face::Uw | Face::Dw | Face::Y => 0,
+            Face::L | Face::R | Face::M | Face::Lw | Face::Rw | Face::X => 1,
+            _ => 2,
+        }
+    }
+
+    // the outer face a move turns the way of: its own, a wide turn's, or the one a slice or rotation follows
+    fn reference(self) -> Face {
+        match self {
+            Face::M => Face::L,
+            Face::E => Face::D,
+            Face::S | Face::Z => Face::F,
+            Face::X => Face::R,
+            Face::Y => Face::U,
+            Face::Uw => Face::U,
+            Face::Dw => Face::D,
+            Face::Lw => Face::L,
+            Face::Rw => Face::R,
+            Face::Fw => Face::F,
+            Face::Bw => Face::B,
+            outer => outer,
+        }
+    }
+
+    // the move of this face's kind that turns the way of the outer face at position, turning the way of dir there
+    fn at_position(self, position: Face, dir: Dir) -> Mov {
+        let face = match self {
+            Face::M | Face::E | Face::S => [Face::E, Face::E, Face::M, Face::M, Face::S, Face::S][position as usize],
+            Face::X | Face::Y | Face::Z => [Face::Y, Face::Y, Face::X, Face::X, Face::Z, Face::Z][position as usize],
+            Face::Uw | Face::Dw | Face::Lw | Face::Rw | Face::Fw | Face::Bw =>
+                [Face::Uw, Face::Dw, Face::Lw, Face::Rw, Face::Fw, Face::Bw][position as usize],
+            _ => position,
+        };
+        let mov = Mov { face, dir };
+        if face.reference() == position { mov } else { mov.inverse() } // the slice or rotation follows the opposite face
+    }
 }
 
 #[derive(Copy, Clone, Eq, PartialEq, Hash, Debug)]
@@ -36,15 +90,29 @@ pub struct Mov {
 }
 
 impl Mov {
-    pub fn to_string(&self) -> String {
-        let mut s = String::new();
-        s.push_str(&format!("{:?}", self.face));
+    pub fn inverse(self) -> Self {
+        let dir = match self.dir {
+            Dir::CW => Dir::CCW,
+            Dir::CCW => Dir::CW,
+            Dir::HT => Dir::HT,
+        };
+        Mov { face: self.face, dir }
+    }
+}
+
+impl fmt::Display for Mov {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self.face {
+            Face::X => write!(f, "x")?,
+            Face::Y => write!(f, "y")?,
+            Face::Z => write!(f, "z")?,
+            _ => write!(f, "{:?}", self.face)?,
+        }
         match self.dir {
-            Dir::CW => s.push_str(""),
-            Dir::CCW => s.push_str("'"),
-            Dir::HT => s.push_str("2"),
+            Dir::CW => Ok(()),
+            Dir::CCW => write!(f, "'"),
+            Dir::HT => write!(f, "2"),
         }
-        s
     }
 }
 
@@ -66,15 +134,23 @@ pub const F2: Mov = Mov { face: Face::F, dir: Dir::HT };
 pub const B: Mov = Mov { face: Face::B, dir: Dir::CW };
 pub const BP: Mov = Mov { face: Face::B, dir: Dir::CCW };
 pub const B2: Mov = Mov { face: Face::B, dir: Dir::HT };
+pub const X: Mov = Mov { face: Face::X, dir: Dir::CW };
+pub const XP: Mov = Mov { face: Face::X, dir: Dir::CCW };
+pub const Y: Mov = Mov { face: Face::Y, dir: Dir::CW };
+pub const YP: Mov = Mov { face: Face::Y, dir: Dir::CCW };
+pub const Z: Mov = Mov { face: Face::Z, dir: Dir::CW };
+pub const ZP: Mov = Mov { face: Face::Z, dir: Dir::CCW };
 
 #[derive(Clone, Eq, PartialEq, Hash, Debug)]
 pub struct Moves(pub Vec<Mov>);
 
-impl Moves {
-    pub fn to_string(&self) -> String {
-        self.0.iter().map(|m| m.to_string()).collect::<Vec<String>>().join(" ")
+impl fmt::Display for Moves {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.0.iter().map(|m| m.to_string()).collect::<Vec<String>>().join(" "))
     }
+}
 
+impl Moves {
     pub fn push(&mut self, mov: Mov) {
         self.0.push(mov);
     }
@@ -82,39 +158,165 @@ impl Moves {
     pub fn extend(&mut self, moves: Moves) {
         self.0.extend(moves.0);
     }
+
+    /*
+       merges consecutive turns of the same face and drops the ones that cancel out;
+       turns about the same axis commute, so each run of them is merged as a whole
+       and written in Face order, which also lets runs on either side of a cancelled
+       run merge with each other
+    */
+    pub fn simplify(&self) -> Moves {
+        fn quarter_turns(dir: Dir) -> u8 {
+            match dir {
+                Dir::CW => 1,
+                Dir::HT => 2,
+                Dir::CCW => 3,
+            }
+        }
+        let mut runs: Vec<Vec<(Face, u8)>> = vec![]; // quarter turns per face, sorted by face
+        for mov in &self.0 {
+            match runs.last_mut() {
+                Some(run) if run[0].0.axis() == mov.face.axis() => {
+                    match run.iter().position(|(face, _)| *face as u8 >= mov.face as u8) {
+                        Some(i) if run[i].0 == mov.face => {
+                            run[i].1 = (run[i].1 + quarter_turns(mov.dir)) % 4;
+                            if run[i].1 == 0 {
+                                run.remove(i);
+                            }
+                        }
+                        Some(i) => run.insert(i, (mov.face, quarter_turns(mov.dir))),
+                        None => run.push((mov.face, quarter_turns(mov.dir))),
+                    }
+                    if run.is_empty() {
+                        runs.pop();
+                    }
+                }
+                _ => runs.push(vec![(mov.face, quarter_turns(mov.dir))]),
+            }
+        }
+        Moves(runs.iter().flatten().map(|(face, turns)| {
+            let dir = match turns {
+                1 => Dir::CW,
+                2 => Dir::HT,
+                _ => Dir::CCW,
+            };
+            Mov { face: *face, dir }
+        }).collect())
+    }
+
+    // half turn metric: any turn of one face counts 1, a slice turn 2 and a rotation 0
+    pub fn htm_length(&self) -> usize {
+        self.0.iter().map(|m| Self::face_turns(m.face)).sum()
+    }
+
+    // quarter turn metric: as the half turn metric, with half turns counting twice
+    pub fn qtm_length(&self) -> usize {
+        self.0.iter().map(|m| Self::face_turns(m.face) * if m.dir == Dir::HT { 2 } else { 1 }).sum()
+    }
+
+    fn face_turns(face: Face) -> usize {
+        match face {
+            Face::M | Face::E | Face::S => 2,
+            Face::X | Face::Y | Face::Z => 0,
+            _ => 1,
+        }
+    }
+
+    pub fn inverse(&self) -> Moves {
+        Moves(self.0.iter().rev().map(|m| m.inverse()).collect())
+    }
+
+    // setup, self, then setup undone: [setup: self]
+    pub fn conjugate(&self, setup: &Moves) -> Moves {
+        let mut moves = setup.clone();
+        moves.extend(self.clone());
+        moves.extend(setup.inverse());
+        moves
+    }
+
+    // a, b, a undone, b undone: [a, b]
+    pub fn commutator(a: &Moves, b: &Moves) -> Moves {
+        let mut moves = a.clone();
+        moves.extend(b.clone());
+        moves.extend(a.inverse());
+        moves.extend(b.inverse());
+        moves
+    }
 }
 
-fn _permute<const N: usize>(permutation: [u8; N], indices: &[u8; 4], clockwise: bool) -> [u8; N] {
-    let mut _perm = permutation.clone();
+fn _permute<T: Copy, const N: usize>(permutation: [T; N], indices: &[u8; 4], clockwise: bool) -> [T; N] {
+    let mut _perm = permutation;
     for i in 0..4 {
-        let p_index;
-        if clockwise {
-            p_index = if i == 0 { 3 } else { i - 1 };
+        let p_index = if clockwise {
+            if i == 0 { 3 } else { i - 1 }
         } else {
-            p_index = if i == 3 { 0 } else { i + 1 };
-        }
+            if i == 3 { 0 } else { i + 1 }
+        };
         _perm[indices[i] as usize] = permutation[indices[p_index] as usize];
     }
-    return _perm;
+    _perm
 }
 
 fn _orient_edges(edge_orientations: [u8; 12], indices: &[u8; 4]) -> [u8; 12] {
-    let mut _edge_orientations = edge_orientations.clone();
+    let mut _edge_orientations = edge_orientations;
     for i in 0..4 {
         _edge_orientations[indices[i] as usize] = (edge_orientations[indices[i] as usize] + 1) % 2;
     }
-    return _edge_orientations;
+    _edge_orientations
 }
 
 fn _orient_corners(corner_orientations: [u8; 8], indices: &[u8; 4]) -> [u8; 8] {
-    let mut _corner_orientations = corner_orientations.clone();
+    let mut _corner_orientations = corner_orientations;
     for i in 0..4 {
         _corner_orientations[indices[i] as usize] =
             (corner_orientations[indices[i] as usize] + ((i as u8 % 2) + 1)) % 3;
     }
-    return _corner_orientations;
+    _corner_orientations
+}
+
+fn _parity(permutation: &[u8]) -> u8 {
+    let mut inversions = 0;
+    for i in 0..permutation.len() {
+        for j in i + 1..permutation.len() {
+            if permutation[i] > permutation[j] {
+                inversions += 1;
+            }
+        }
+    }
+    inversions % 2
+}
+
+#[derive(Copy, Clone, Eq, PartialEq, Hash, Debug)]
+pub enum InvalidCube {
+    InvalidCorner { position: &'static str, cubie: u8 },
+    InvalidEdge { position: &'static str, cubie: u8 },
+    DuplicateCorner(&'static str),
+    DuplicateEdge(&'static str),
+    InvalidCornerOrientation { position: &'static str, orientation: u8 },
+    InvalidEdgeOrientation { position: &'static str, orientation: u8 },
+    TwistedCorners(u8), // sum of corner orientations mod 3
+    FlippedEdges,
+    ParityMismatch,
+}
+
+impl fmt::Display for InvalidCube {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            InvalidCube::InvalidCorner { position, cubie } => write!(f, "corner at {} is {}, expected 0 to 7", position, cubie),
+            InvalidCube::InvalidEdge { position, cubie } => write!(f, "edge at {} is {}, expected 0 to 11", position, cubie),
+            InvalidCube::DuplicateCorner(cubie) => write!(f, "corner cubie {} appears more than once", cubie),
+            InvalidCube::DuplicateEdge(cubie) => write!(f, "edge cubie {} appears more than once", cubie),
+            InvalidCube::InvalidCornerOrientation { position, orientation } => write!(f, "corner at {} has orientation {}, expected 0 to 2", position, orientation),
+            InvalidCube::InvalidEdgeOrientation { position, orientation } => write!(f, "edge at {} has orientation {}, expected 0 or 1", position, orientation),
+            InvalidCube::TwistedCorners(twist) => write!(f, "corner orientations sum to {} mod 3, a corner is twisted", twist),
+            InvalidCube::FlippedEdges => write!(f, "edge orientations sum to 1 mod 2, an edge is flipped"),
+            InvalidCube::ParityMismatch => write!(f, "corner and edge permutation parities differ, two cubies are swapped"),
+        }
+    }
 }
 
+impl std::error::Error for InvalidCube {}
+
 #[derive(Copy, Clone, Eq, PartialEq, Hash, Debug)]
 pub struct Cube {
     pub corner_orientations: CornerOrientations, // 3 orientations per corner
@@ -122,6 +324,13 @@ pub struct Cube {
     pub edge_orientations: EdgeOrientations,     // 2 orientations per edge
     pub edge_permutations: EdgePermutations,     // 12 edges
     pub prev_move: Option<Mov>,                  // used to avoid repeating the same face move in bfs
+    pub centers: Centers,                        // moved only by slice, wide and rotation moves
+}
+
+impl Default for Cube {
+    fn default() -> Self {
+        Self::new()
+    }
 }
 
 impl Cube {
@@ -144,6 +353,11 @@ impl Cube {
     const B_CORNER_INDICES: [u8; 4] = [3, 2, 6, 7];
     const B_EDGE_INDICES: [u8; 4] = [2, 6, 10, 7];
 
+    // center positions in the order each rotation cycles them
+    const X_CENTER_INDICES: [u8; 4] = [Face::F as u8, Face::U as u8, Face::B as u8, Face::D as u8];
+    const Y_CENTER_INDICES: [u8; 4] = [Face::F as u8, Face::L as u8, Face::B as u8, Face::R as u8];
+    const Z_CENTER_INDICES: [u8; 4] = [Face::U as u8, Face::R as u8, Face::D as u8, Face::L as u8];
+
     pub const LR_SLICE_EDGES: [u8; 4] = [0, 2, 8, 10];
     pub const UD_SLICE_EDGES: [u8; 4] = [4, 5, 6, 7];
     pub const FB_SLICE_EDGES: [u8; 4] = [1, 3, 9, 11];
@@ -153,6 +367,11 @@ impl Cube {
     pub const TETRAD_1_CORNERS: [u8; 4] = [0, 2, 5, 7];
     pub const TETRAD_2_CORNERS: [u8; 4] = [1, 3, 4, 6];
 
+    pub const STANDARD_CENTERS: Centers = [Face::U, Face::D, Face::L, Face::R, Face::F, Face::B];
+
+    pub const CORNER_NAMES: [&str; 8] = ["URF", "UFL", "ULB", "UBR", "DRF", "DFL", "DLB", "DBR"];
+    pub const EDGE_NAMES: [&str; 12] = ["UF", "UL", "UB", "UR", "FR", "FL", "BL", "BR", "DF", "DL", "DB", "DR"];
+
     pub fn new() -> Self {
         Self {
             corner_orientations: [0; 8], // all corners are oriented 0
@@ -160,11 +379,12 @@ impl Cube {
             edge_orientations: [0; 12],  // all edges are oriented 0
             edge_permutations: (0..12).collect::<Vec<u8>>().try_into().unwrap(),  // 0 to 11
             prev_move: None,
+            centers: Self::STANDARD_CENTERS,
         }
     }
 
     pub fn u(self, clockwise: bool, mov: Option<Mov>) -> Self {
-        return Self {
+        Self {
             corner_orientations: _permute(
                 _orient_corners(self.corner_orientations, &Self::U_CORNER_INDICES),
                 &Self::U_CORNER_INDICES,
@@ -182,11 +402,12 @@ impl Cube {
             ),
             edge_permutations: _permute(self.edge_permutations, &Self::U_EDGE_INDICES, clockwise),
             prev_move: mov,
-        };
+            centers: self.centers,
+        }
     }
 
     pub fn d(self, clockwise: bool, mov: Option<Mov>) -> Self {
-        return Self {
+        Self {
             corner_orientations: _permute(
                 _orient_corners(self.corner_orientations, &Self::D_CORNER_INDICES),
                 &Self::D_CORNER_INDICES,
@@ -204,11 +425,12 @@ impl Cube {
             ),
             edge_permutations: _permute(self.edge_permutations, &Self::D_EDGE_INDICES, clockwise),
             prev_move: mov,
-        };
+            centers: self.centers,
+        }
     }
 
     pub fn l(self, clockwise: bool, mov: Option<Mov>) -> Self {
-        return Self {
+        Self {
             corner_orientations: _permute(
                 self.corner_orientations,
                 &Self::L_CORNER_INDICES,
@@ -222,11 +444,12 @@ impl Cube {
             edge_orientations: _permute(self.edge_orientations, &Self::L_EDGE_INDICES, clockwise),
             edge_permutations: _permute(self.edge_permutations, &Self::L_EDGE_INDICES, clockwise),
             prev_move: mov,
-        };
+            centers: self.centers,
+        }
     }
 
     pub fn r(self, clockwise: bool, mov: Option<Mov>) -> Self {
-        return Self {
+        Self {
             corner_orientations: _permute(
                 self.corner_orientations,
                 &Self::R_CORNER_INDICES,
@@ -240,11 +463,12 @@ impl Cube {
             edge_orientations: _permute(self.edge_orientations, &Self::R_EDGE_INDICES, clockwise),
             edge_permutations: _permute(self.edge_permutations, &Self::R_EDGE_INDICES, clockwise),
             prev_move: mov,
-        };
+            centers: self.centers,
+        }
     }
 
     pub fn f(self, clockwise: bool, mov: Option<Mov>) -> Self {
-        return Self {
+        Self {
             corner_orientations: _permute(
                 _orient_corners(self.corner_orientations, &Self::F_CORNER_INDICES),
                 &Self::F_CORNER_INDICES,
@@ -258,11 +482,12 @@ impl Cube {
             edge_orientations: _permute(self.edge_orientations, &Self::F_EDGE_INDICES, clockwise),
             edge_permutations: _permute(self.edge_permutations, &Self::F_EDGE_INDICES, clockwise),
             prev_move: mov,
-        };
+            centers: self.centers,
+        }
     }
 
     pub fn b(self, clockwise: bool, mov: Option<Mov>) -> Self {
-        return Self {
+        Self {
             corner_orientations: _permute(
                 _orient_corners(self.corner_orientations, &Self::B_CORNER_INDICES),
                 &Self::B_CORNER_INDICES,
@@ -276,31 +501,60 @@ impl Cube {
             edge_orientations: _permute(self.edge_orientations, &Self::B_EDGE_INDICES, clockwise),
             edge_permutations: _permute(self.edge_permutations, &Self::B_EDGE_INDICES, clockwise),
             prev_move: mov,
-        };
+            centers: self.centers,
+        }
     }
 
-    pub fn char_to_face(c: char) -> Option<Face> {
-        match c {
-            'u' | 'U' => Some(Face::U), 'd' | 'D' => Some(Face::D),
-            'l' | 'L' => Some(Face::L), 'r' | 'R' => Some(Face::R),
-            'f' | 'F' => Some(Face::F), 'b' | 'B' => Some(Face::B),
-            _ => None,
+    pub fn rotate(self, face: Face, clockwise: bool, mov: Option<Mov>) -> Self {
+        let indices = match face {
+            Face::X => &Self::X_CENTER_INDICES,
+            Face::Y => &Self::Y_CENTER_INDICES,
+            _ => &Self::Z_CENTER_INDICES,
+        };
+        Self {
+            prev_move: mov,
+            centers: _permute(self.centers, indices, clockwise),
+            ..self
         }
     }
 
-    fn char_to_dir(c: char) -> Option<Dir> {
-        match c {
-            '\'' => Some(Dir::CCW),
-            '2' => Some(Dir::HT),
-            _ => None,
+    // slice and wide moves as clockwise outer moves followed by a clockwise or counterclockwise rotation
+    fn decompose(face: Face) -> (&'static [Mov], Mov) {
+        match face {
+            Face::M => (&[R, LP], XP),
+            Face::E => (&[U, DP], YP),
+            Face::S => (&[FP, B], Z),
+            Face::Uw => (&[D], Y),
+            Face::Dw => (&[U], YP),
+            Face::Lw => (&[R], XP),
+            Face::Rw => (&[L], X),
+            Face::Fw => (&[B], Z),
+            _ => (&[F], ZP), // Bw
         }
     }
 
     pub fn apply_move(self, mov: Mov) -> Self {
-        let mut c = match mov.face {
-            Face::U => self.u(mov.dir == Dir::CW, Some(mov)), Face::D => self.d(mov.dir == Dir::CW, Some(mov)),
-            Face::L => self.l(mov.dir == Dir::CW, Some(mov)), Face::R => self.r(mov.dir == Dir::CW, Some(mov)),
-            Face::F => self.f(mov.dir == Dir::CW, Some(mov)), Face::B => self.b(mov.dir == Dir::CW, Some(mov)),
+        if mov.dir == Dir::HT && !mov.face.is_outer() {
+            let quarter = Mov { face: mov.face, dir: Dir::CW };
+            return self.apply_move(quarter).apply_move(quarter);
+        }
+        let clockwise = mov.dir == Dir::CW;
+        if matches!(mov.face, Face::X | Face::Y | Face::Z) {
+            return self.rotate(mov.face, clockwise, Some(mov));
+        }
+        if !mov.face.is_outer() {
+            let (outer_moves, rotation) = Self::decompose(mov.face);
+            let mut c = self;
+            for m in outer_moves {
+                c = c.apply_move(if clockwise { *m } else { m.inverse() });
+            }
+            return c.apply_move(if clockwise { rotation } else { rotation.inverse() });
+        }
+        // outer moves are named by position, turn whichever face the centers put there
+        let mut c = match self.centers[mov.face as usize] {
+            Face::U => self.u(clockwise, Some(mov)), Face::D => self.d(clockwise, Some(mov)),
+            Face::L => self.l(clockwise, Some(mov)), Face::R => self.r(clockwise, Some(mov)),
+            Face::F => self.f(clockwise, Some(mov)), _ => self.b(clockwise, Some(mov)),
         };
         if mov.dir == Dir::HT {
             c = c.apply_move(Mov { face: mov.face, dir: Dir::CCW });
@@ -308,21 +562,30 @@ impl Cube {
         c
     }
 
-    pub fn apply_sequence(self, s: &str) -> Self {
-        let mut cube = self;
-        let mut chars = s.chars().peekable();
-        while let Some(char) = chars.next() {
-            if let Some(face) = Self::char_to_face(char) {
-                let mut dir = Dir::CW;
-                if let Some(&next_char) = chars.peek() {
-                    if let Some(d) = Self::char_to_dir(next_char) {
-                        dir = d;
-                    }
-                }
-                cube = cube.apply_move(Mov{face, dir});
-            }
-        }
-        cube
+    // same corners and edges, with move names following the center colors again
+    pub fn reset_centers(self) -> Self {
+        Self { centers: Self::STANDARD_CENTERS, ..self }
+    }
+
+    /*
+       restates moves for a cube with standard centers as the moves turning the same corners and edges
+       of self: each is renamed after the position self's centers moved the face it turns the way of to.
+       slice, wide and rotation moves move the centers of both, which is followed along the moves
+    */
+    pub fn reframe_moves(self, moves: Moves) -> Moves {
+        let (mut standard, mut cube) = (self.reset_centers(), self);
+        Moves(moves.0.into_iter().map(|m| {
+            let face = standard.centers[m.face.reference() as usize];
+            let position = cube.centers.iter().position(|c| *c == face).unwrap(); // the centers are a permutation
+            let mov = m.face.at_position(Self::STANDARD_CENTERS[position], m.dir);
+            standard = standard.apply_move(m);
+            cube = cube.apply_move(mov);
+            mov
+        }).collect())
+    }
+
+    pub fn apply_sequence(self, s: &str) -> Result<Self, ParseError> {
+        Ok(self.apply_moves(Moves::parse(s)?))
     }
 
     pub fn apply_moves(self, moves: Moves) -> Self {
@@ -334,6 +597,11 @@ impl Cube {
     }
 
     pub fn scramble(self, n: u32) -> (Self, Moves) {
+        self.scramble_with(&mut rand::rng(), n)
+    }
+
+    // same as scramble, drawing the moves from rng, so a seeded rng reproduces the scramble
+    pub fn scramble_with(self, rng: &mut impl Rng, n: u32) -> (Self, Moves) {
         fn index_to_face(index: u8) -> Face {
             match index {
                 0 => Face::U,
@@ -354,7 +622,6 @@ impl Cube {
             }
         }
         let mut cube = self;
-        let mut rng = rand::rng();
         let mut scrambled_moves = Moves(vec![]);
         let mut moves_applied_count = 0;
         while moves_applied_count < n {
@@ -370,6 +637,129 @@ impl Cube {
         }
         (cube, scrambled_moves)
     }
+
+    /*
+       a uniformly random state reachable by face moves: permutations are shuffled and the
+       orientations drawn freely, except the last edge and corner which are fixed by the flip
+       and twist sums, and swapping two edges fixes an odd edge parity to match the corners
+    */
+    pub fn random_state_with(rng: &mut impl Rng) -> Self {
+        let mut cube = Self::new();
+        cube.corner_permutations.shuffle(rng);
+        cube.edge_permutations.shuffle(rng);
+        if _parity(&cube.corner_permutations) != _parity(&cube.edge_permutations) {
+            cube.edge_permutations.swap(0, 1);
+        }
+        for i in 0..7 {
+            cube.corner_orientations[i] = rng.random_range(0..3);
+        }
+        cube.corner_orientations[7] = (3 - cube.corner_orientations[..7].iter().sum::<u8>() % 3) % 3;
+        for i in 0..11 {
+            cube.edge_orientations[i] = rng.random_range(0..2);
+        }
+        cube.edge_orientations[11] = cube.edge_orientations[..11].iter().sum::<u8>() % 2;
+        cube
+    }
+}
+
+impl Cube {
+    // checks that the state is reachable from a solved cube by face moves
+    pub fn validate(self) -> Result<(), InvalidCube> {
+        let mut corner_seen = [false; 8];
+        for (position, &cubie) in self.corner_permutations.iter().enumerate() {
+            if cubie >= 8 {
+                return Err(InvalidCube::InvalidCorner { position: Self::CORNER_NAMES[position], cubie });
+            }
+            if corner_seen[cubie as usize] {
+                return Err(InvalidCube::DuplicateCorner(Self::CORNER_NAMES[cubie as usize]));
+            }
+            corner_seen[cubie as usize] = true;
+        }
+        let mut edge_seen = [false; 12];
+        for (position, &cubie) in self.edge_permutations.iter().enumerate() {
+            if cubie >= 12 {
+                return Err(InvalidCube::InvalidEdge { position: Self::EDGE_NAMES[position], cubie });
+            }
+            if edge_seen[cubie as usize] {
+                return Err(InvalidCube::DuplicateEdge(Self::EDGE_NAMES[cubie as usize]));
+            }
+            edge_seen[cubie as usize] = true;
+        }
+        for (position, &orientation) in self.corner_orientations.iter().enumerate() {
+            if orientation >= 3 {
+                return Err(InvalidCube::InvalidCornerOrientation { position: Self::CORNER_NAMES[position], orientation });
+            }
+        }
+        for (position, &orientation) in self.edge_orientations.iter().enumerate() {
+            if orientation >= 2 {
+                return Err(InvalidCube::InvalidEdgeOrientation { position: Self::EDGE_NAMES[position], orientation });
+            }
+        }
+        let twist = self.corner_orientations.iter().sum::<u8>() % 3;
+        if twist != 0 {
+            return Err(InvalidCube::TwistedCorners(twist));
+        }
+        if self.edge_orientations.iter().sum::<u8>() % 2 != 0 {
+            return Err(InvalidCube::FlippedEdges);
+        }
+        if _parity(&self.corner_permutations) != _parity(&self.edge_permutations) {
+            return Err(InvalidCube::ParityMismatch);
+        }
+        Ok(())
+    }
+}
+
+impl Cube {
+    /*
+       group operations on the corners and edges; centers and prev_move of the results
+       are those of a new cube. self.compose(&other) is self followed by other, so
+       for moves a and b: new().apply_moves(a).compose(&new().apply_moves(b)) == new().apply_moves(a + b)
+    */
+    pub fn compose(self, other: &Cube) -> Self {
+        let mut cube = Self::new();
+        for i in 0..8 {
+            let from = other.corner_permutations[i] as usize;
+            cube.corner_permutations[i] = self.corner_permutations[from];
+            cube.corner_orientations[i] = (self.corner_orientations[from] + other.corner_orientations[i]) % 3;
+        }
+        for i in 0..12 {
+            let from = other.edge_permutations[i] as usize;
+            cube.edge_permutations[i] = self.edge_permutations[from];
+            cube.edge_orientations[i] = (self.edge_orientations[from] + other.edge_orientations[i]) % 2;
+        }
+        cube
+    }
+
+    pub fn inverse(self) -> Self {
+        let mut cube = Self::new();
+        for i in 0..8 {
+            let cubie = self.corner_permutations[i] as usize;
+            cube.corner_permutations[cubie] = i as u8;
+            cube.corner_orientations[cubie] = (3 - self.corner_orientations[i]) % 3;
+        }
+        for i in 0..12 {
+            let cubie = self.edge_permutations[i] as usize;
+            cube.edge_permutations[cubie] = i as u8;
+            cube.edge_orientations[cubie] = (2 - self.edge_orientations[i]) % 2;
+        }
+        cube
+    }
+
+    // number of times the state has to be applied to return to solved, at most 1260
+    pub fn order(self) -> Result<u32, InvalidCube> {
+        self.validate()?;
+        let solved = Self::new();
+        let mut cube = self;
+        let mut order = 1;
+        while cube.corner_permutations != solved.corner_permutations
+            || cube.corner_orientations != solved.corner_orientations
+            || cube.edge_permutations != solved.edge_permutations
+            || cube.edge_orientations != solved.edge_orientations {
+            cube = cube.compose(&self);
+            order += 1;
+        }
+        Ok(order)
+    }
 }
 
 impl fmt::Display for Cube {
@@ -0,0 +1,107 @@
+use std::fmt;
+use std::io;
+use std::path::PathBuf;
+
+use crate::cube::InvalidCube;
+use crate::facelet::FaceletError;
+use crate::notation::ParseError;
+use crate::report::SolveReport;
+
+/*
+   errors of the library as a whole. table errors name the file and how to fix it;
+   a solve that finds no solution still returns its report, with the phases it got through.
+*/
+#[derive(Debug)]
+pub enum Error {
+    MissingTable { path: PathBuf },
+    CorruptTable { path: PathBuf, reason: String },
+    VersionMismatch { path: PathBuf, version: u16, expected: u16 },
+    DefinitionMismatch { path: PathBuf }, // generated for other moves or coordinates
+    Io { path: PathBuf, source: io::Error }, // reading or writing a file failed otherwise
+    InvalidCube(InvalidCube),
+    Parse(ParseError),
+    InvalidFacelets(FaceletError),
+    SearchExhausted(Box<SolveReport>), // every depth the solver searches was tried
+    Timeout(Box<SolveReport>), // the time budget ran out before a first solution
+}
+
+impl Error {
+    // an error accessing a table file
+    pub fn io(path: impl Into<PathBuf>, source: io::Error) -> Self {
+        let path = path.into();
+        if source.kind() == io::ErrorKind::NotFound {
+            Error::MissingTable { path }
+        } else {
+            Error::Io { path, source }
+        }
+    }
+
+    pub fn is_table_error(&self) -> bool {
+        matches!(self, Error::MissingTable { .. } | Error::CorruptTable { .. } | Error::VersionMismatch { .. }
+            | Error::DefinitionMismatch { .. } | Error::Io { .. })
+    }
+
+    // the report of a solve that found no solution
+    pub fn report(&self) -> Option<&SolveReport> {
+        match self {
+            Error::SearchExhausted(report) | Error::Timeout(report) => Some(report),
+            _ => None,
+        }
+    }
+
+    // process exit code for the CLI: 3 table or file, 4 invalid input, 5 no solution found
+    pub fn exit_code(&self) -> u8 {
+        match self {
+            _ if self.is_table_error() => 3,
+            Error::InvalidCube(_) | Error::Parse(_) | Error::InvalidFacelets(_) => 4,
+            _ => 5,
+        }
+    }
+}
+
+impl fmt::Display for Error {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Error::MissingTable { path } => write!(f, "prune table {} is missing, generate it with prune-gen", path.display()),
+            Error::CorruptTable { path, reason } => write!(f, "prune table {} is corrupt ({}), regenerate it with prune-gen", path.display(), reason),
+            Error::VersionMismatch { path, version, expected } => write!(f, "prune table {} has version {}, expected {}, regenerate it with prune-gen", path.display(), version, expected),
+            Error::DefinitionMismatch { path } => write!(f, "prune table {} was generated for different moves or coordinates, regenerate it with prune-gen", path.display()),
+            Error::Io { path, source } => write!(f, "{}: {}", path.display(), source),
+            Error::InvalidCube(e) => write!(f, "invalid cube: {}", e),
+            Error::Parse(e) => write!(f, "parse error: {}", e),
+            Error::InvalidFacelets(e) => write!(f, "invalid facelets: {}", e),
+            Error::SearchExhausted(report) => write!(f, "{} solver found no solution within its search depth", report.solver),
+            Error::Timeout(report) => write!(f, "{} solver found no solution within its time budget", report.solver),
+        }
+    }
+}
+
+impl std::error::Error for Error {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            Error::Io { source, .. } => Some(source),
+            Error::InvalidCube(e) => Some(e),
+            Error::Parse(e) => Some(e),
+            Error::InvalidFacelets(e) => Some(e),
+            _ => None,
+        }
+    }
+}
+
+impl From<InvalidCube> for Error {
+    fn from(e: InvalidCube) -> Self {
+        Error::InvalidCube(e)
+    }
+}
+
+impl From<ParseError> for Error {
+    fn from(e: ParseError) -> Self {
+        Error::Parse(e)
+    }
+}
+
+impl From<FaceletError> for Error {
+    fn from(e: FaceletError) -> Self {
+        Error::InvalidFacelets(e)
+    }
+}
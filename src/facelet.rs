@@ -0,0 +1,251 @@
+use std::fmt;
+
+use crate::cube::{Centers, Cube, Dir, Face, Moves, X, XP, Y, Z, ZP};
+
+/*
+   facelet string: 54 characters, faces in URFDLB order, 9 stickers per face read row by row
+   as seen on the standard net:
+
+                 U1 U2 U3
+                 U4 U5 U6
+                 U7 U8 U9
+       L1 L2 L3  F1 F2 F3  R1 R2 R3  B1 B2 B3
+       L4 L5 L6  F4 F5 F6  R4 R5 R6  B4 B5 B6
+       L7 L8 L9  F7 F8 F9  R7 R8 R9  B7 B8 B9
+                 D1 D2 D3
+                 D4 D5 D6
+                 D7 D8 D9
+
+   the colors are mapped to faces by the centers, so any 6 distinct characters work.
+   to_facelets shows the cube as it is held, with the centers where slice, wide and rotation
+   moves took them; from_facelets reads it back with the centers as the standard ones.
+*/
+
+const FACE_CHARS: [char; 6] = ['U', 'R', 'F', 'D', 'L', 'B'];
+const CENTER_FACELETS: [usize; 6] = [4, 13, 22, 31, 40, 49];
+
+/*
+   facelets of each corner position in clockwise order, starting from the L/R facelet;
+   a corner with orientation o has its L/R sticker o steps counterclockwise from there
+*/
+const CORNER_FACELETS: [[usize; 3]; 8] = [
+    [9, 20, 8],   // URF: R1 F3 U9
+    [38, 6, 18],  // UFL: L3 U7 F1
+    [36, 47, 0],  // ULB: L1 B3 U1
+    [11, 2, 45],  // UBR: R3 U3 B1
+    [15, 29, 26], // DRF: R7 D3 F9
+    [44, 24, 27], // DFL: L9 F7 D1
+    [42, 33, 53], // DLB: L7 D7 B9
+    [17, 51, 35], // DBR: R9 B7 D9
+];
+
+/*
+   facelets of each edge position, starting from the L/R facelet, or the U/D facelet
+   for edges without one; a flipped edge has its first sticker on the second facelet
+*/
+const EDGE_FACELETS: [[usize; 2]; 12] = [
+    [7, 19],  // UF: U8 F2
+    [37, 3],  // UL: L2 U4
+    [1, 46],  // UB: U2 B2
+    [10, 5],  // UR: R2 U6
+    [12, 23], // FR: R4 F6
+    [41, 21], // FL: L6 F4
+    [39, 50], // BL: L4 B6
+    [14, 48], // BR: R6 B4
+    [28, 25], // DF: D2 F8
+    [43, 30], // DL: L8 D4
+    [34, 52], // DB: D8 B8
+    [16, 32], // DR: R8 D6
+];
+
+/*
+   the facelet each clockwise whole cube rotation moves to each facelet, one face per row:
+   after x, facelet i shows what facelet X_FACELETS[i] showed before
+*/
+const X_FACELETS: [usize; 54] = [
+    18, 19, 20, 21, 22, 23, 24, 25, 26,
+    15, 12, 9, 16, 13, 10, 17, 14, 11,
+    27, 28, 29, 30, 31, 32, 33, 34, 35,
+    53, 52, 51, 50, 49, 48, 47, 46, 45,
+    38, 41, 44, 37, 40, 43, 36, 39, 42,
+    8, 7, 6, 5, 4, 3, 2, 1, 0,
+];
+const Y_FACELETS: [usize; 54] = [
+    6, 3, 0, 7, 4, 1, 8, 5, 2,
+    45, 46, 47, 48, 49, 50, 51, 52, 53,
+    9, 10, 11, 12, 13, 14, 15, 16, 17,
+    29, 32, 35, 28, 31, 34, 27, 30, 33,
+    18, 19, 20, 21, 22, 23, 24, 25, 26,
+    36, 37, 38, 39, 40, 41, 42, 43, 44,
+];
+const Z_FACELETS: [usize; 54] = [
+    42, 39, 36, 43, 40, 37, 44, 41, 38,
+    6, 3, 0, 7, 4, 1, 8, 5, 2,
+    24, 21, 18, 25, 22, 19, 26, 23, 20,
+    15, 12, 9, 16, 13, 10, 17, 14, 11,
+    33, 30, 27, 34, 31, 28, 35, 32, 29,
+    47, 50, 53, 46, 49, 52, 45, 48, 51,
+];
+
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub enum FaceletError {
+    InvalidLength(usize),
+    DuplicateCenter(char),
+    UnknownColor { index: usize, color: char },
+    ColorCount { color: char, count: usize },
+    InvalidCorner { position: &'static str, colors: String },
+    InvalidEdge { position: &'static str, colors: String },
+    DuplicateCorner(&'static str),
+    DuplicateEdge(&'static str),
+}
+
+impl fmt::Display for FaceletError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            FaceletError::InvalidLength(len) => write!(f, "expected 54 facelets, got {}", len),
+            FaceletError::DuplicateCenter(color) => write!(f, "color '{}' appears on more than one center", color),
+            FaceletError::UnknownColor { index, color } => write!(f, "facelet {} has color '{}' which is not on any center", index, color),
+            FaceletError::ColorCount { color, count } => write!(f, "color '{}' appears {} times, expected 9", color, count),
+            FaceletError::InvalidCorner { position, colors } => write!(f, "corner at {} has colors {} which match no corner cubie", position, colors),
+            FaceletError::InvalidEdge { position, colors } => write!(f, "edge at {} has colors {} which match no edge cubie", position, colors),
+            FaceletError::DuplicateCorner(cubie) => write!(f, "corner cubie {} appears more than once", cubie),
+            FaceletError::DuplicateEdge(cubie) => write!(f, "edge cubie {} appears more than once", cubie),
+        }
+    }
+}
+
+impl std::error::Error for FaceletError {}
+
+// face of the sticker a solved cube has on the given facelet
+fn home_face(facelet: usize) -> u8 {
+    (facelet / 9) as u8
+}
+
+fn is_lr_face(face: u8) -> bool {
+    FACE_CHARS[face as usize] == 'L' || FACE_CHARS[face as usize] == 'R'
+}
+
+impl Cube {
+    pub fn from_facelets(s: &str) -> Result<Self, FaceletError> {
+        let chars: Vec<char> = s.trim().chars().collect();
+        if chars.len() != 54 {
+            return Err(FaceletError::InvalidLength(chars.len()));
+        }
+
+        let mut center_colors = ['\0'; 6];
+        for (face, &facelet) in CENTER_FACELETS.iter().enumerate() {
+            if center_colors.contains(&chars[facelet]) {
+                return Err(FaceletError::DuplicateCenter(chars[facelet]));
+            }
+            center_colors[face] = chars[facelet];
+        }
+        let mut faces = [0u8; 54];
+        let mut counts = [0usize; 6];
+        for (index, &color) in chars.iter().enumerate() {
+            let Some(face) = center_colors.iter().position(|c| *c == color) else {
+                return Err(FaceletError::UnknownColor { index, color });
+            };
+            faces[index] = face as u8;
+            counts[face] += 1;
+        }
+        for (face, &count) in counts.iter().enumerate() {
+            if count != 9 {
+                return Err(FaceletError::ColorCount { color: center_colors[face], count });
+            }
+        }
+        let colors_of = |facelets: &[usize]| facelets.iter().map(|f| chars[*f]).collect::<String>();
+
+        let mut cube = Cube::new();
+        for (position, facelets) in CORNER_FACELETS.iter().enumerate() {
+            let position_faces = facelets.map(|f| faces[f]);
+            let invalid = || FaceletError::InvalidCorner { position: Cube::CORNER_NAMES[position], colors: colors_of(facelets) };
+            let lr_index = position_faces.iter().position(|f| is_lr_face(*f)).ok_or_else(invalid)?;
+            let cubie_faces = [0, 1, 2].map(|k| position_faces[(k + lr_index) % 3]);
+            let cubie = CORNER_FACELETS.iter()
+                .position(|home| home.map(home_face) == cubie_faces)
+                .ok_or_else(invalid)?;
+            if cube.corner_permutations[..position].contains(&(cubie as u8)) {
+                return Err(FaceletError::DuplicateCorner(Cube::CORNER_NAMES[cubie]));
+            }
+            cube.corner_permutations[position] = cubie as u8;
+            cube.corner_orientations[position] = ((3 - lr_index) % 3) as u8;
+        }
+        for (position, facelets) in EDGE_FACELETS.iter().enumerate() {
+            let position_faces = facelets.map(|f| faces[f]);
+            let mut found = None;
+            for (cubie, home) in EDGE_FACELETS.iter().enumerate() {
+                let home_faces = home.map(home_face);
+                if home_faces == position_faces {
+                    found = Some((cubie, 0));
+                } else if home_faces == [position_faces[1], position_faces[0]] {
+                    found = Some((cubie, 1));
+                }
+            }
+            let Some((cubie, orientation)) = found else {
+                return Err(FaceletError::InvalidEdge { position: Cube::EDGE_NAMES[position], colors: colors_of(facelets) });
+            };
+            if cube.edge_permutations[..position].contains(&(cubie as u8)) {
+                return Err(FaceletError::DuplicateEdge(Cube::EDGE_NAMES[cubie]));
+            }
+            cube.edge_permutations[position] = cubie as u8;
+            cube.edge_orientations[position] = orientation;
+        }
+        Ok(cube)
+    }
+
+    /*
+       the rotations taking a cube with standard centers to centers, tilting a face to U, then turning
+       about U; none for centers that are no rotation of the standard ones, those are shown as standard
+    */
+    fn rotations_to(centers: Centers) -> Moves {
+        let tilts = [vec![], vec![X], vec![X, X], vec![XP], vec![Z], vec![ZP]];
+        for tilt in tilts {
+            let mut rotations = Moves(tilt);
+            for _ in 0..4 {
+                if Cube::new().apply_moves(rotations.clone()).centers == centers {
+                    return rotations;
+                }
+                rotations.push(Y);
+            }
+        }
+        Moves(vec![])
+    }
+
+    pub fn to_facelets(self) -> String {
+        let mut faces = [0u8; 54];
+        for (face, &facelet) in CENTER_FACELETS.iter().enumerate() {
+            faces[facelet] = face as u8;
+        }
+        for (position, facelets) in CORNER_FACELETS.iter().enumerate() {
+            let cubie = self.corner_permutations[position] as usize;
+            let orientation = self.corner_orientations[position] as usize;
+            for k in 0..3 {
+                faces[facelets[(k + 3 - orientation) % 3]] = home_face(CORNER_FACELETS[cubie][k]);
+            }
+        }
+        for (position, facelets) in EDGE_FACELETS.iter().enumerate() {
+            let cubie = self.edge_permutations[position] as usize;
+            let orientation = self.edge_orientations[position] as usize;
+            for k in 0..2 {
+                faces[facelets[(k + orientation) % 2]] = home_face(EDGE_FACELETS[cubie][k]);
+            }
+        }
+        // the stickers above are placed for standard centers, turn the whole cube to where its centers are
+        for rotation in Self::rotations_to(self.centers).0 {
+            let from = match rotation.face {
+                Face::X => &X_FACELETS,
+                Face::Y => &Y_FACELETS,
+                _ => &Z_FACELETS,
+            };
+            let quarter_turns = match rotation.dir {
+                Dir::CW => 1,
+                Dir::HT => 2,
+                Dir::CCW => 3,
+            };
+            for _ in 0..quarter_turns {
+                faces = from.map(|f| faces[f]);
+            }
+        }
+        faces.iter().map(|f| FACE_CHARS[*f as usize]).collect()
+    }
+}
@@ -0,0 +1,320 @@
+use std::path::Path;
+use std::time::{Duration, Instant};
+
+use rand::Rng;
+
+use crate::context::SolverContext;
+use crate::coord::{self, Phase, PhaseDefinition};
+use crate::cube::{self, Cube, Mov};
+use crate::error::Error;
+use crate::profile;
+use crate::prune_table::PruneTable;
+use crate::report::{PhaseReport, SolveReport};
+use crate::solver::{is_redundant, Solver};
+
+/*
+   Kociemba two-phase solver.
+   phase 1 brings the cube from G0 into the domino subgroup <L, R, U2, D2, F2, B2>
+   (G2_MOVES): edge orientations 0, corner orientations 0 and LR slice edges in the LR slice.
+   phase 2 solves the cube within that subgroup using G2_MOVES only.
+   both phases are IDA* searches over coordinates stepped with the phases' move tables;
+   after the first solution the search keeps going with longer phase 1 solutions until
+   the total length or time budget is reached.
+*/
+
+const MAX_PHASE1_DEPTH: usize = 12;
+const MAX_PHASE2_DEPTH: usize = 18;
+const RANDOM_STATE_TARGET_LENGTH: usize = 22; // most states have solutions this short, found in milliseconds
+const DEADLINE_CHECK_INTERVAL: u64 = 1024; // phase 2 nodes expanded between deadline checks
+
+struct KociembaSearch<'a> {
+    tables: &'a KociembaTables,
+    cube: Cube, // phase 1 starts here, phase 2 from it with the phase 1 moves applied
+    target_length: usize,
+    deadline: Instant,
+    timed_out: bool, // set by phase 2 when it passed the deadline
+    phase1_moves: Vec<Mov>,
+    phase2_moves: Vec<Mov>,
+    best: Option<(cube::Moves, cube::Moves)>,
+    profile: &'a profile::Profile,
+    phase1_report: PhaseReport, // node counts only, the moves and time are filled in at the end
+    phase2_report: PhaseReport, // node counts and time spent in phase 2 over all phase 1 solutions
+}
+
+impl KociembaSearch<'_> {
+    fn best_length(&self) -> usize {
+        match &self.best {
+            Some((moves_p1, moves_p2)) => moves_p1.0.len() + moves_p2.0.len(),
+            None => usize::MAX,
+        }
+    }
+
+    fn is_done(&self) -> bool {
+        self.timed_out || self.best_length() <= self.target_length || Instant::now() >= self.deadline
+    }
+
+    fn phase1_heuristic(&self, co: u32, eo: u32) -> usize {
+        let co = self.tables.p1_co.get_depth(co).unwrap_or(0);
+        let eo = self.tables.p1_eo.get_depth(eo).unwrap_or(0);
+        co.max(eo) as usize
+    }
+
+    fn phase2_heuristic(&self, cp: u32, ep: u32) -> usize {
+        let cp = self.tables.p2_cp.get_depth(cp).unwrap_or(0);
+        let ep = self.tables.p2_ep.get_depth(ep).unwrap_or(0);
+        cp.max(ep) as usize
+    }
+
+    // co and eo are the p1_co and p1_eo phase indices, returns true once the search should stop
+    fn search_phase1(&mut self, co: u32, eo: u32, depth_left: usize) -> bool {
+        if self.is_done() {
+            return true;
+        }
+        let h = self.phase1_heuristic(co, eo);
+        if h > depth_left {
+            self.phase1_report.pruned_cnt += 1;
+            return false;
+        }
+        let prev_move = self.phase1_moves.last().copied();
+        if depth_left == 0 {
+            // a phase 1 solution ending in a phase 2 move has a shorter counterpart already tried
+            if prev_move.is_none_or(|m| !Solver::G2_MOVES.contains(&m)) {
+                let start = Instant::now();
+                self.start_phase2();
+                self.phase2_report.duration += start.elapsed();
+            }
+            return self.is_done();
+        }
+        self.phase1_report.expanded_cnt += 1;
+        let tables = self.tables;
+        for (i, &m) in tables.p1_co_phase.moves.iter().enumerate() { // both phase 1 phases have the same moves
+            if is_redundant(prev_move, m) {
+                continue;
+            }
+            self.phase1_moves.push(m);
+            let stop = self.search_phase1(tables.p1_co_phase.apply(co, i), tables.p1_eo_phase.apply(eo, i), depth_left - 1);
+            self.phase1_moves.pop();
+            if stop {
+                return true;
+            }
+        }
+        false
+    }
+
+    fn start_phase2(&mut self) {
+        let phase1_length = self.phase1_moves.len();
+        let best_length = self.best_length();
+        if phase1_length >= best_length {
+            return;
+        }
+        let cube = self.cube.apply_moves(cube::Moves(self.phase1_moves.clone()));
+        let (cp, ep) = (Solver::get_kociemba_p2_cp_index(cube), Solver::get_kociemba_p2_ep_index(cube));
+        let max_depth = MAX_PHASE2_DEPTH.min(best_length - phase1_length - 1);
+        for depth in self.phase2_heuristic(cp, ep)..=max_depth {
+            if self.timed_out {
+                self.phase2_moves.clear();
+                return;
+            }
+            if self.search_phase2(cp, ep, depth) {
+                self.best = Some((cube::Moves(self.phase1_moves.clone()), cube::Moves(self.phase2_moves.clone())));
+                self.phase2_moves.clear();
+                self.profile.report(&format!("found {} move solution", self.best_length()));
+                return;
+            }
+        }
+    }
+
+    // cp and ep are the p2_cp and p2_ep phase indices,
+    // returns true once phase 2 is solved, false when it is not or the deadline passed
+    fn search_phase2(&mut self, cp: u32, ep: u32, depth_left: usize) -> bool {
+        let h = self.phase2_heuristic(cp, ep);
+        if h == 0 {
+            return true;
+        }
+        if h > depth_left {
+            self.phase2_report.pruned_cnt += 1;
+            return false;
+        }
+        self.phase2_report.expanded_cnt += 1;
+        if self.phase2_report.expanded_cnt.is_multiple_of(DEADLINE_CHECK_INTERVAL) && Instant::now() >= self.deadline {
+            self.timed_out = true;
+        }
+        if self.timed_out {
+            return false;
+        }
+        let prev_move = self.phase2_moves.last().or(self.phase1_moves.last()).copied();
+        let tables = self.tables;
+        for (i, &m) in tables.p2_cp_phase.moves.iter().enumerate() { // both phase 2 phases have the same moves
+            if is_redundant(prev_move, m) {
+                continue;
+            }
+            self.phase2_moves.push(m);
+            if self.search_phase2(tables.p2_cp_phase.apply(cp, i), tables.p2_ep_phase.apply(ep, i), depth_left - 1) {
+                return true;
+            }
+            self.phase2_moves.pop();
+            if self.timed_out {
+                return false;
+            }
+        }
+        false
+    }
+}
+
+impl Solver { // Kociemba two-phase solver, the indices are laid out like the phases' own
+    pub fn get_kociemba_p1_co_index(cube: Cube) -> u32 {
+        Self::get_g1_index(cube) // corner orientations and LR slice combination
+    }
+
+    pub fn get_kociemba_p1_eo_index(cube: Cube) -> u32 {
+        coord::edge_orientation(cube) * 495 + coord::lr_slice_combination(cube) // 495: comb(12, 4)
+    }
+
+    pub fn get_kociemba_p2_cp_index(cube: Cube) -> u32 {
+        coord::corner_permutation(cube) * 24 + coord::lr_slice_permutation(cube) // 24: perm(4, 4)
+    }
+
+    pub fn get_kociemba_p2_ep_index(cube: Cube) -> u32 {
+        coord::non_lr_slice_edge_permutation(cube) * 24 + coord::lr_slice_permutation(cube) // 24: perm(4, 4)
+    }
+
+    pub fn solve_kociemba(cube: Cube, name: String, print_moves: bool, target_length: usize, time_budget: Duration) -> Result<SolveReport, Error> {
+        SolverContext::new().solve_kociemba(cube, name, print_moves, target_length, time_budget)
+    }
+
+    pub(crate) fn kociemba_p1_co_definition() -> PhaseDefinition {
+        PhaseDefinition::new(&Self::G0_MOVES, &[coord::LR_SLICE_COMBINATION, coord::CORNER_ORIENTATION])
+    }
+
+    pub fn gen_prune_table_kociemba_p1_co() -> PruneTable {
+        Self::gen_prune_table("gen_prune_table_kociemba_p1_co".to_string(), &Self::kociemba_p1_co_definition().build())
+    }
+
+    pub(crate) fn kociemba_p1_eo_definition() -> PhaseDefinition {
+        PhaseDefinition::new(&Self::G0_MOVES, &[coord::LR_SLICE_COMBINATION, coord::EDGE_ORIENTATION])
+    }
+
+    pub fn gen_prune_table_kociemba_p1_eo() -> PruneTable {
+        Self::gen_prune_table("gen_prune_table_kociemba_p1_eo".to_string(), &Self::kociemba_p1_eo_definition().build())
+    }
+
+    pub(crate) fn kociemba_p2_cp_definition() -> PhaseDefinition {
+        PhaseDefinition::new(&Self::G2_MOVES, &[coord::LR_SLICE_PERMUTATION, coord::CORNER_PERMUTATION])
+    }
+
+    pub fn gen_prune_table_kociemba_p2_cp() -> PruneTable {
+        Self::gen_prune_table("gen_prune_table_kociemba_p2_cp".to_string(), &Self::kociemba_p2_cp_definition().build())
+    }
+
+    pub(crate) fn kociemba_p2_ep_definition() -> PhaseDefinition {
+        PhaseDefinition::new(&Self::G2_MOVES, &[coord::LR_SLICE_PERMUTATION, coord::NON_LR_SLICE_EDGE_PERMUTATION])
+    }
+
+    pub fn gen_prune_table_kociemba_p2_ep() -> PruneTable {
+        Self::gen_prune_table("gen_prune_table_kociemba_p2_ep".to_string(), &Self::kociemba_p2_ep_definition().build())
+    }
+}
+
+pub(crate) struct KociembaTables {
+    pub p1_co_phase: Phase,
+    pub p1_eo_phase: Phase,
+    pub p2_cp_phase: Phase,
+    pub p2_ep_phase: Phase,
+    pub p1_co: PruneTable,
+    pub p1_eo: PruneTable,
+    pub p2_cp: PruneTable,
+    pub p2_ep: PruneTable,
+}
+
+impl KociembaTables {
+    pub fn load(dir: &Path) -> Result<Self, Error> {
+        Ok(Self { // tables first, so a missing one fails before the move tables are built
+            p1_co: PruneTable::load_kociemba_p1_co(dir)?,
+            p1_eo: PruneTable::load_kociemba_p1_eo(dir)?,
+            p2_cp: PruneTable::load_kociemba_p2_cp(dir)?,
+            p2_ep: PruneTable::load_kociemba_p2_ep(dir)?,
+            p1_co_phase: Solver::kociemba_p1_co_definition().build(),
+            p1_eo_phase: Solver::kociemba_p1_eo_definition().build(),
+            p2_cp_phase: Solver::kociemba_p2_cp_definition().build(),
+            p2_ep_phase: Solver::kociemba_p2_ep_definition().build(),
+        })
+    }
+}
+
+impl SolverContext { // Kociemba two-phase solver
+    pub fn solve_kociemba(&self, cube: Cube, name: String, print_moves: bool, target_length: usize, time_budget: Duration) -> Result<SolveReport, Error> {
+        Self::solve_reframed(cube, |frame, cube| {
+            let p = profile::Profile::start(&name, print_moves);
+            let tables = self.kociemba_tables()?;
+            p.report("prune tables loaded");
+
+            let (co, eo) = (Solver::get_kociemba_p1_co_index(cube), Solver::get_kociemba_p1_eo_index(cube));
+            let mut search = KociembaSearch {
+                tables,
+                cube,
+                target_length,
+                deadline: Instant::now() + time_budget,
+                timed_out: false,
+                phase1_moves: vec![],
+                phase2_moves: vec![],
+                best: None,
+                profile: &p,
+                phase1_report: PhaseReport::new("phase1"),
+                phase2_report: PhaseReport::new("phase2"),
+            };
+            for depth in search.phase1_heuristic(co, eo)..=MAX_PHASE1_DEPTH {
+                if depth >= search.best_length() || search.search_phase1(co, eo, depth) {
+                    break;
+                }
+            }
+
+            let mut report = SolveReport::new("kociemba", cube);
+            let (mut phase1_report, mut phase2_report) = (search.phase1_report, search.phase2_report);
+            phase1_report.duration = p.elapsed().saturating_sub(phase2_report.duration);
+            let Some((moves_p1, moves_p2)) = search.best else {
+                report.phases = vec![phase1_report, phase2_report];
+                let report = Box::new(report.finish(false, cube::Moves(vec![]), p.elapsed()));
+                if Instant::now() >= search.deadline {
+                    p.report("no solution found within time budget");
+                    return Err(Error::Timeout(report));
+                }
+                p.report("no solution found");
+                return Err(Error::SearchExhausted(report));
+            };
+            phase1_report.success = true;
+            phase1_report.moves = moves_p1.clone();
+            phase2_report.success = true;
+            phase2_report.moves = moves_p2.clone();
+            report.phases = vec![phase1_report, phase2_report];
+            let mut moves = moves_p1.clone();
+            moves.extend(moves_p2.clone());
+            if print_moves {
+                let (moves_p1, moves_p2) = (frame.reframe_moves(moves_p1), frame.reframe_moves(moves_p2));
+                println!("Phase 1 Moves: {}", moves_p1);
+                println!("{}", frame.apply_moves(moves_p1.clone()));
+                println!("Phase 2 Moves: {}", moves_p2);
+                println!("{}", frame.apply_moves(moves_p1).apply_moves(moves_p2));
+            }
+            let duration = p.end();
+            if print_moves { println!("Full solution ({} moves): {}", moves.0.len(), frame.reframe_moves(moves.clone())); }
+            Ok(report.finish(true, moves, duration))
+        })
+    }
+
+    /*
+       a WCA-style scramble: a uniformly random state, reached by the inverse of a solution to it.
+       unlike random moves this samples every state equally; a search running out of time is
+       retried on the same state with a longer budget so no state is less likely than others
+    */
+    pub fn scramble_random_state(&self, rng: &mut impl Rng) -> Result<(Cube, cube::Moves), Error> {
+        let cube = Cube::random_state_with(rng);
+        let mut time_budget = Duration::from_millis(100);
+        loop {
+            match self.solve_kociemba(cube, "scramble_random_state".to_string(), false, RANDOM_STATE_TARGET_LENGTH, time_budget) {
+                Ok(report) => return Ok((cube, report.solution.inverse())),
+                Err(Error::Timeout(_)) => time_budget *= 2,
+                Err(e) => return Err(e),
+            }
+        }
+    }
+}
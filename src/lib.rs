@@ -0,0 +1,32 @@
+/*
+   Rubik's cube model and solvers.
+   Cube and Moves model the cube and move sequences, with parsing from notation and facelets.
+   SolverContext owns the prune tables and solves cubes with the Thistlethwaite, Kociemba or
+   optimal solver chosen in SolveOptions, returning a SolveReport.
+   the modules stay public for the lower level pieces: prune table generation, phase indices,
+   profiling and batch runs.
+*/
+
+pub mod batch;
+pub mod context;
+mod coord;
+pub mod cube;
+pub mod error;
+mod facelet;
+mod kociemba;
+mod notation;
+mod optimal;
+pub mod profile;
+pub mod prune_table;
+pub mod report;
+pub mod solver;
+#[cfg(test)]
+mod test;
+
+pub use crate::context::{SolveOptions, SolverContext, SolverKind};
+pub use crate::cube::{Cube, Dir, Face, InvalidCube, Mov, Moves};
+pub use crate::error::Error;
+pub use crate::facelet::FaceletError;
+pub use crate::notation::{ParseError, ParseErrorKind};
+pub use crate::prune_table::PruneTable;
+pub use crate::report::{PhaseReport, SolveReport};
@@ -1,73 +1,416 @@
-use std::{env, io};
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+use std::process::ExitCode;
+use std::time::Duration;
 
-mod cube;
-mod solver;
-mod profile;
-mod prune_table;
+use clap::{Args, Parser, Subcommand, ValueEnum};
+use rand::SeedableRng;
+use rand::rngs::StdRng;
 
-use crate::cube::*;
+use rubiks_solver::cube::{self, *};
+use rubiks_solver::{batch, prune_table, solver};
+use rubiks_solver::{Error, SolveOptions, SolveReport, SolverContext, SolverKind};
 
-fn main() {
-    let args: Vec<String> = env::args().collect();
-    if args.len() != 2 {
-        println!("Usage: {} [sim|solve-rand|solve-fixed|debug]", args[0]);
-        return;
+#[derive(Parser)]
+#[command(about = "Rubik's cube simulator and solver")]
+struct Cli {
+    /// Directory prune tables are read from, and generated into when missing or unusable
+    /// [default: $RUBIKS_SOLVER_TABLE_DIR, else $XDG_CACHE_HOME/rubiks-solver or ~/.cache/rubiks-solver]
+    #[arg(long, global = true)]
+    table_dir: Option<PathBuf>,
+    #[command(subcommand)]
+    command: Command,
+}
+
+#[derive(Subcommand)]
+enum Command {
+    /// Apply move sequences read from stdin, empty line to dump the cube
+    Sim,
+    /// Solve a scramble, a facelet state, the fixed example or a random scramble
+    Solve(SolveArgs),
+    /// Print a random scramble and the state it leads to
+    Scramble(ScrambleArgs),
+    /// Solve random scrambles in parallel and report success rate, lengths, times and phase lengths
+    Stats(StatsArgs),
+    /// Solve one scramble or facelet state per line of a file or stdin
+    Batch(BatchArgs),
+    /// Generate prune tables
+    PruneGen(PruneGenArgs),
+    /// Check every prune table in the table directory against its checksum and definition
+    VerifyTables,
+    /// Print the phase indices of a solved cube
+    Debug,
+}
+
+#[derive(Copy, Clone, ValueEnum)]
+enum SolverArg {
+    Thistlethwaite,
+    Kociemba,
+    Optimal,
+}
+
+#[derive(Copy, Clone, PartialEq, ValueEnum)]
+enum OutputFormat {
+    Text,
+    Json,
+}
+
+#[derive(Args)]
+struct SolverArgs {
+    #[arg(long, value_enum, default_value_t = SolverArg::Thistlethwaite)]
+    solver: SolverArg,
+    /// Kociemba: stop looking for shorter solutions once one is at most this long
+    #[arg(long, default_value_t = 20)]
+    max_length: usize,
+    /// Kociemba: time in milliseconds to keep looking for shorter solutions
+    #[arg(long, default_value_t = 500)]
+    time_budget: u64,
+}
+
+#[derive(Args)]
+struct SolveArgs {
+    /// Solve the state these moves lead to from a solved cube
+    #[arg(long, conflicts_with_all = ["facelets", "fixed"])]
+    scramble: Option<String>,
+    /// Solve the state given as 54 facelets in URFDLB face order
+    #[arg(long, conflicts_with = "fixed")]
+    facelets: Option<String>,
+    /// Find the moves from --facelets to this facelet state instead of to solved
+    #[arg(long, requires = "facelets")]
+    target: Option<String>,
+    /// Solve the fixed 25 move example scramble
+    #[arg(long)]
+    fixed: bool,
+    /// Length of the random scramble solved when no state is given [default: 25, 10 for optimal]
+    #[arg(long)]
+    length: Option<u32>,
+    /// Seed of the random scramble, to replay a scramble printed by an earlier run
+    #[arg(long, conflicts_with_all = ["scramble", "facelets", "fixed"])]
+    seed: Option<u64>,
+    /// Solve a uniformly random state instead of a random move sequence
+    #[arg(long, conflicts_with_all = ["scramble", "facelets", "fixed", "length"])]
+    random_state: bool,
+    /// Print the solve as text or as a JSON report
+    #[arg(long, value_enum, default_value_t = OutputFormat::Text)]
+    format: OutputFormat,
+    #[command(flatten)]
+    solver: SolverArgs,
+}
+
+#[derive(Args)]
+struct ScrambleArgs {
+    #[arg(long, default_value_t = 25)]
+    length: u32,
+    /// Seed for a reproducible scramble
+    #[arg(long)]
+    seed: Option<u64>,
+    /// Scramble to a uniformly random state instead of applying random moves
+    #[arg(long, conflicts_with = "length")]
+    random_state: bool,
+}
+
+#[derive(Args)]
+struct StatsArgs {
+    #[arg(long, default_value_t = 100)]
+    trials: u32,
+    #[arg(long, default_value_t = 1)]
+    threads: u32,
+    /// Length of each random scramble
+    #[arg(long, default_value_t = 25)]
+    length: u32,
+    /// Seed of the first trial, trial i uses seed + i; random by default
+    #[arg(long)]
+    seed: Option<u64>,
+    /// Solve uniformly random states instead of random move sequences
+    #[arg(long, conflicts_with = "length")]
+    random_state: bool,
+    #[command(flatten)]
+    solver: SolverArgs,
+}
+
+#[derive(Copy, Clone, PartialEq, ValueEnum)]
+enum BatchFormat {
+    Csv,
+    Jsonl,
+}
+
+#[derive(Args)]
+struct BatchArgs {
+    /// File with one move sequence or 54 facelet string per line; stdin if omitted or -
+    #[arg(long)]
+    input: Option<PathBuf>,
+    #[arg(long, value_enum, default_value_t = BatchFormat::Csv)]
+    format: BatchFormat,
+    #[arg(long, default_value_t = 1)]
+    threads: u32,
+    #[command(flatten)]
+    solver: SolverArgs,
+}
+
+#[derive(Copy, Clone, PartialEq, ValueEnum)]
+enum TableSet {
+    G0,
+    G1,
+    G2,
+    G3,
+    Kociemba,
+    Optimal,
+    All,
+}
+
+#[derive(Args)]
+struct PruneGenArgs {
+    /// Directory to write the tables to [default: the table directory]
+    #[arg(long)]
+    out: Option<PathBuf>,
+    /// Tables to generate, may be repeated
+    #[arg(long, value_enum, default_values_t = [TableSet::All])]
+    phase: Vec<TableSet>,
+}
+
+const FIXED_MOVES: [Mov; 25] = [L2, U, F2, DP, F, U, F, D2, BP, F2, RP, BP, U2, RP, D2, R, L, DP, U, D, L2, DP, UP, B2, F];
+
+impl SolverArgs {
+    fn options(&self, print_moves: bool) -> SolveOptions {
+        SolveOptions {
+            solver: match self.solver {
+                SolverArg::Thistlethwaite => SolverKind::Thistlethwaite,
+                SolverArg::Kociemba => SolverKind::Kociemba,
+                SolverArg::Optimal => SolverKind::Optimal,
+            },
+            target_length: self.max_length,
+            time_budget: Duration::from_millis(self.time_budget),
+            print_moves,
+        }
     }
-    match args[1].as_str() {
-        "sim" => {
-            let mut cube = cube::Cube::new();
-            println!("Enter move sequences, empty line to dump and continue:");
-            let mut buffer = String::new();
-            while let Ok(_) = io::stdin().read_line(&mut buffer) {
-                let line = buffer.trim();
-                if line.is_empty() {
-                    println!("{}", cube);
-                } else {
-                    cube = cube.apply_sequence(line);
+
+    fn solve(&self, context: &SolverContext, cube: Cube) -> Result<SolveReport, Error> {
+        context.solve(cube, &self.options(false))
+    }
+}
+
+fn run_sim() {
+    let mut cube = cube::Cube::new();
+    println!("Enter move sequences, empty line to dump and continue:");
+    let mut buffer = String::new();
+    while let Ok(n) = io::stdin().read_line(&mut buffer) && n > 0 {
+        let line = buffer.trim();
+        if line.is_empty() {
+            println!("{}", cube);
+            println!("Facelets: {}", cube.to_facelets());
+            if let Ok(order) = cube.order() {
+                println!("Order: {}", order);
+            }
+        } else {
+            match cube.apply_sequence(line) {
+                Ok(c) => {
+                    cube = c;
                     println!("Applied: {}", line);
                     println!("{}", cube);
+                    println!("Facelets: {}", cube.to_facelets());
                 }
-                buffer.clear();
+                Err(e) => println!("Parse error: {}", e),
             }
         }
-        "solve-rand" => {
-            let (cube, scrambled_moves) = cube::Cube::new().scramble(25);
-            println!("Scrambled moves: {}", scrambled_moves.to_string());
-            println!("{}", cube);
-            solver::Solver::solve_thistlethwaite(cube, "solve-rand".to_string(), true);
+        buffer.clear();
+    }
+}
+
+fn run_solve(context: &SolverContext, args: &SolveArgs) -> Result<(), Error> {
+    let text = args.format == OutputFormat::Text;
+    let cube = if let Some(scramble) = &args.scramble {
+        let cube = cube::Cube::new().apply_sequence(scramble)?;
+        if text { println!("Scrambled moves: {}", scramble); }
+        cube
+    } else if let Some(facelets) = &args.facelets {
+        let from = cube::Cube::from_facelets(facelets)?;
+        match args.target.as_deref() {
+            // moves m with from * m == to solve the cube to^-1 * from
+            Some(target) => cube::Cube::from_facelets(target)?.inverse().compose(&from),
+            None => from,
         }
-        "solve-rand-stat" => {
-            let trials = 100;
-            let mut success_count = 0;
-            for i in 0..trials {
-                let (cube, _) = cube::Cube::new().scramble(25);
-                let (success, _) = solver::Solver::solve_thistlethwaite(cube, "solve-rand".to_string(), false);
-                if success {
-                    success_count += 1;
-                }
-                println!("Trial {}: Success rate: {:.2}%", i + 1, success_count as f32 / (i + 1) as f32 * 100.0);
-            }
+    } else if args.fixed {
+        let moves = cube::Moves(FIXED_MOVES.to_vec());
+        if text { println!("Fixed moves: {}", moves); }
+        cube::Cube::new().apply_moves(moves)
+    } else {
+        let default_length = if matches!(args.solver.solver, SolverArg::Optimal) { 10 } else { 25 };
+        let length = args.length.unwrap_or(default_length);
+        let seed = args.seed.unwrap_or_else(rand::random);
+        let (cube, scrambled_moves) = if args.random_state {
+            context.scramble_random_state(&mut StdRng::seed_from_u64(seed))?
+        } else {
+            cube::Cube::new().scramble_with(&mut StdRng::seed_from_u64(seed), length)
+        };
+        if text {
+            println!("Seed: {}", seed);
+            println!("Scrambled moves: {}", scrambled_moves);
+        } else {
+            eprintln!("Seed: {}", seed); // keep stdout valid JSON
         }
-        "solve-fixed" => {
-            let moves = cube::Moves(vec![L2, U, F2, DP, F, U, F, D2, BP, F2, RP, BP, U2, RP, D2, R, L, DP, U, D, L2, DP, UP, B2, F]);
-            let cube = cube::Cube::new().apply_moves(moves.clone());
-            println!("Fixed moves: {}", moves.to_string());
-            println!("{}", cube);
-            solver::Solver::solve_thistlethwaite(cube, "solve-fixed".to_string(), true);
+        cube
+    };
+    if text { println!("{}", cube); }
+    let result = context.solve(cube, &args.solver.options(text));
+    if !text && let Some(report) = result.as_ref().ok().or(result.as_ref().err().and_then(Error::report)) {
+        println!("{}", serde_json::to_string_pretty(report).unwrap());
+    }
+    result.map(|_| ())
+}
+
+fn run_scramble(context: &SolverContext, args: &ScrambleArgs) -> Result<(), Error> {
+    let (cube, scrambled_moves) = match (args.random_state, args.seed) {
+        (true, Some(seed)) => context.scramble_random_state(&mut StdRng::seed_from_u64(seed))?,
+        (true, None) => context.scramble_random_state(&mut rand::rng())?,
+        (false, Some(seed)) => cube::Cube::new().scramble_with(&mut StdRng::seed_from_u64(seed), args.length),
+        (false, None) => cube::Cube::new().scramble(args.length),
+    };
+    println!("Scrambled moves: {}", scrambled_moves);
+    println!("{}", cube);
+    println!("Facelets: {}", cube.to_facelets());
+    Ok(())
+}
+
+fn run_stats(context: &SolverContext, args: &StatsArgs) -> Result<(), Error> {
+    let seed = args.seed.unwrap_or_else(rand::random);
+    println!("Seed: {}", seed);
+    context.preload(args.solver.options(false).solver)?;
+    if args.random_state {
+        context.preload(SolverKind::Kociemba)?;
+    }
+    let results = batch::run(args.trials as usize, args.threads as usize, |trial| {
+        let trial_seed = seed.wrapping_add(trial as u64);
+        let mut rng = StdRng::seed_from_u64(trial_seed);
+        let (cube, _) = if args.random_state {
+            context.scramble_random_state(&mut rng)?
+        } else {
+            cube::Cube::new().scramble_with(&mut rng, args.length)
+        };
+        let result = args.solver.solve(context, cube);
+        if let Err(e) = &result && e.report().is_some() {
+            let solver = args.solver.solver.to_possible_value().unwrap();
+            let scramble = if args.random_state { "--random-state".to_string() } else { format!("--length {}", args.length) };
+            println!("Trial {} failed, replay with: solve --solver {} --seed {} {}", trial, solver.get_name(), trial_seed, scramble);
+        }
+        result
+    });
+    // failed solves still count, with their reports; anything else, like a missing table, stops the run
+    let mut reports = vec![];
+    for result in results {
+        match result {
+            Ok(report) => reports.push(report),
+            Err(Error::SearchExhausted(report) | Error::Timeout(report)) => reports.push(*report),
+            Err(e) => return Err(e),
+        }
+    }
+    println!("{}", batch::BatchStats::new(&reports));
+    Ok(())
+}
+
+// a line without whitespace and 54 characters long is a facelet string, anything else a move sequence
+fn parse_batch_line(line: &str) -> Result<Cube, Error> {
+    if line.len() == 54 && !line.contains(char::is_whitespace) {
+        Ok(cube::Cube::from_facelets(line)?)
+    } else {
+        Ok(cube::Cube::new().apply_sequence(line)?)
+    }
+}
+
+fn run_batch(context: &SolverContext, args: &BatchArgs) -> Result<(), Error> {
+    let input = match args.input.as_deref() {
+        Some(path) if path != Path::new("-") => fs::read_to_string(path).map_err(|source| Error::Io { path: path.into(), source })?,
+        _ => io::read_to_string(io::stdin()).map_err(|source| Error::Io { path: "stdin".into(), source })?,
+    };
+    // line numbers are kept for the output, blank lines are skipped
+    let lines: Vec<(usize, &str)> = input.lines().map(str::trim).enumerate()
+        .filter(|(_, line)| !line.is_empty())
+        .map(|(i, line)| (i + 1, line))
+        .collect();
+
+    context.preload(args.solver.options(false).solver)?;
+    let results = batch::run(lines.len(), args.threads as usize, |i| {
+        let (line_number, line) = lines[i];
+        let result = parse_batch_line(line).and_then(|cube| args.solver.solve(context, cube));
+        (line_number, line, result)
+    });
+
+    if args.format == BatchFormat::Csv {
+        println!("{}", batch::BatchLine::CSV_HEADER);
+    }
+    for (line_number, line, result) in results {
+        // a bad line is reported in its output line, a table error would fail every line
+        if let Err(e) = &result && e.is_table_error() {
+            return Err(result.unwrap_err());
         }
-        "prune-gen" => {
-            prune_table::PruneTable::gen_g1();
-            prune_table::PruneTable::gen_g2();
-            prune_table::PruneTable::gen_g3();
+        let result = batch::BatchLine::new(line_number, line, result);
+        match args.format {
+            BatchFormat::Csv => println!("{}", result.to_csv()),
+            BatchFormat::Jsonl => println!("{}", serde_json::to_string(&result).unwrap()),
         }
-        "debug" => {
+    }
+    Ok(())
+}
+
+fn run_prune_gen(context: &SolverContext, args: &PruneGenArgs) -> Result<(), Error> {
+    let dir = args.out.as_deref().unwrap_or(context.table_dir());
+    fs::create_dir_all(dir).map_err(|source| Error::Io { path: dir.to_path_buf(), source })?;
+    let selected = |set| args.phase.contains(&set) || args.phase.contains(&TableSet::All);
+    if selected(TableSet::G0) { prune_table::PruneTable::gen_g0(dir)?; }
+    if selected(TableSet::G1) { prune_table::PruneTable::gen_g1(dir)?; }
+    if selected(TableSet::G2) { prune_table::PruneTable::gen_g2(dir)?; }
+    if selected(TableSet::G3) { prune_table::PruneTable::gen_g3(dir)?; }
+    if selected(TableSet::Kociemba) { prune_table::PruneTable::gen_kociemba(dir)?; }
+    if selected(TableSet::Optimal) { prune_table::PruneTable::gen_optimal(dir)?; }
+    Ok(())
+}
+
+// reports every table, then fails with the first error
+fn run_verify_tables(context: &SolverContext) -> Result<(), Error> {
+    let mut first_error = None;
+    for (path, result) in prune_table::PruneTable::verify_all(context.table_dir()) {
+        match result {
+            Ok(_) => println!("OK   {}", path.display()),
+            Err(e) => {
+                println!("FAIL {}: {}", path.display(), e);
+                first_error.get_or_insert(e);
+            }
+        }
+    }
+    first_error.map_or(Ok(()), Err)
+}
+
+fn main() -> ExitCode {
+    let cli = Cli::parse();
+    // one context for the whole run, so its tables are loaded once
+    let context = match &cli.table_dir {
+        Some(dir) => SolverContext::with_table_dir(dir),
+        None => SolverContext::new(),
+    };
+    let result = match &cli.command {
+        Command::Sim => {
+            run_sim();
+            Ok(())
+        }
+        Command::Solve(args) => run_solve(&context, args),
+        Command::Scramble(args) => run_scramble(&context, args),
+        Command::Stats(args) => run_stats(&context, args),
+        Command::Batch(args) => run_batch(&context, args),
+        Command::PruneGen(args) => run_prune_gen(&context, args),
+        Command::VerifyTables => run_verify_tables(&context),
+        Command::Debug => {
             let cube = cube::Cube::new();
-            println!("{}", solver::Solver::get_g1_index(cube));
-            println!("{}", solver::Solver::get_g2_index(cube));
-            println!("{}", solver::Solver::get_g3_index(cube));
+            for index in solver::Solver::phase_indices(cube) {
+                println!("{}", index);
+            }
+            Ok(())
         }
-        _ => {
-            println!("Usage: {} [sim|solve-rand|solve-fixed|prune-gen|debug]", args[0]);
+    };
+    match result {
+        Ok(()) => ExitCode::SUCCESS,
+        Err(e) => {
+            eprintln!("error: {}", e);
+            ExitCode::from(e.exit_code())
         }
     }
 }
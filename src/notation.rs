@@ -0,0 +1,173 @@
+use std::fmt;
+use std::iter::Peekable;
+use std::str::CharIndices;
+
+use crate::cube::{Dir, Face, Mov, Moves};
+
+/*
+   move notation:
+     outer turns   U D L R F B
+     wide turns    Uw Dw Lw Rw Fw Bw, or u d l r f b
+     slices        M E S
+     rotations     x y z
+   each optionally followed by ' (counterclockwise), 2 or 2' (half turn).
+   moves may be grouped with parentheses and repeated, e.g. (R U R' U')3,
+   written as a commutator [A, B] = A B A' B' or a conjugate [A: B] = A B A',
+   and everything from // to the end of the line is a comment.
+*/
+
+#[derive(Copy, Clone, Eq, PartialEq, Hash, Debug)]
+pub enum ParseErrorKind {
+    UnexpectedCharacter,
+    InvalidMove,
+    UnclosedGroup,
+    UnmatchedBracket,
+    InvalidRepetition,
+}
+
+#[derive(Clone, Eq, PartialEq, Hash, Debug)]
+pub struct ParseError {
+    pub offset: usize, // byte offset of the token in the input
+    pub token: String,
+    pub kind: ParseErrorKind,
+}
+
+impl fmt::Display for ParseError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let reason = match self.kind {
+            ParseErrorKind::UnexpectedCharacter => "unexpected character",
+            ParseErrorKind::InvalidMove => "invalid move",
+            ParseErrorKind::UnclosedGroup => "group is never closed",
+            ParseErrorKind::UnmatchedBracket => "no group to close",
+            ParseErrorKind::InvalidRepetition => "invalid repetition count",
+        };
+        write!(f, "{} '{}' at byte {}", reason, self.token, self.offset)
+    }
+}
+
+impl std::error::Error for ParseError {}
+
+fn char_to_face(c: char) -> Option<Face> {
+    match c {
+        'U' => Some(Face::U), 'D' => Some(Face::D),
+        'L' => Some(Face::L), 'R' => Some(Face::R),
+        'F' => Some(Face::F), 'B' => Some(Face::B),
+        'u' => Some(Face::Uw), 'd' => Some(Face::Dw),
+        'l' => Some(Face::Lw), 'r' => Some(Face::Rw),
+        'f' => Some(Face::Fw), 'b' => Some(Face::Bw),
+        'M' => Some(Face::M), 'E' => Some(Face::E), 'S' => Some(Face::S),
+        'x' | 'X' => Some(Face::X), 'y' | 'Y' => Some(Face::Y), 'z' | 'Z' => Some(Face::Z),
+        _ => None,
+    }
+}
+
+fn wide_face(face: Face) -> Option<Face> {
+    match face {
+        Face::U => Some(Face::Uw), Face::D => Some(Face::Dw),
+        Face::L => Some(Face::Lw), Face::R => Some(Face::Rw),
+        Face::F => Some(Face::Fw), Face::B => Some(Face::Bw),
+        _ => None,
+    }
+}
+
+fn is_suffix_char(c: char) -> bool {
+    c == 'w' || c == '\'' || c == '’' || c.is_ascii_digit()
+}
+
+struct Parser<'a> {
+    input: &'a str,
+    chars: Peekable<CharIndices<'a>>,
+}
+
+impl Parser<'_> {
+    fn error(&self, offset: usize, end: usize, kind: ParseErrorKind) -> ParseError {
+        ParseError { offset, token: self.input[offset..end].to_string(), kind }
+    }
+
+    fn end_of(&mut self, start: usize, condition: fn(char) -> bool) -> usize {
+        while let Some(&(_, c)) = self.chars.peek() {
+            if !condition(c) {
+                break;
+            }
+            self.chars.next();
+        }
+        self.chars.peek().map_or(self.input.len(), |(i, _)| *i).max(start)
+    }
+
+    // a group is the whole input when open_offset is None, or the inside of a bracket up to one of closers
+    fn parse_group(&mut self, open_offset: Option<usize>, closers: &[char]) -> Result<(Moves, char), ParseError> {
+        let mut moves = Moves(vec![]);
+        while let Some(&(offset, c)) = self.chars.peek() {
+            if c.is_whitespace() {
+                self.chars.next();
+            } else if c == '/' {
+                self.chars.next();
+                if self.chars.peek().map(|(_, c)| *c) != Some('/') {
+                    return Err(self.error(offset, offset + 1, ParseErrorKind::UnexpectedCharacter));
+                }
+                self.end_of(offset, |c| c != '\n');
+            } else if c == '(' {
+                self.chars.next();
+                let (group, _) = self.parse_group(Some(offset), &[')'])?;
+                let count_offset = self.chars.peek().map_or(self.input.len(), |(i, _)| *i);
+                let count_end = self.end_of(count_offset, |c| c.is_ascii_digit());
+                let count = if count_end == count_offset {
+                    1
+                } else {
+                    match self.input[count_offset..count_end].parse::<usize>() {
+                        Ok(count) if count > 0 => count,
+                        _ => return Err(self.error(count_offset, count_end, ParseErrorKind::InvalidRepetition)),
+                    }
+                };
+                for _ in 0..count {
+                    moves.extend(group.clone());
+                }
+            } else if c == '[' {
+                self.chars.next();
+                let (a, separator) = self.parse_group(Some(offset), &[',', ':'])?;
+                let (b, _) = self.parse_group(Some(offset), &[']'])?;
+                moves.extend(if separator == ',' { Moves::commutator(&a, &b) } else { b.conjugate(&a) });
+            } else if closers.contains(&c) {
+                self.chars.next();
+                return Ok((moves, c));
+            } else if matches!(c, ')' | ']' | ',' | ':') {
+                let kind = if open_offset.is_some() || matches!(c, ')' | ']') {
+                    ParseErrorKind::UnmatchedBracket
+                } else {
+                    ParseErrorKind::UnexpectedCharacter
+                };
+                return Err(self.error(offset, offset + 1, kind));
+            } else if let Some(face) = char_to_face(c) {
+                self.chars.next();
+                let end = self.end_of(offset, is_suffix_char);
+                let token = &self.input[offset..end];
+                let mut suffix = &token[c.len_utf8()..];
+                let mut face = face;
+                if let Some(rest) = suffix.strip_prefix('w') {
+                    face = wide_face(face).ok_or_else(|| self.error(offset, end, ParseErrorKind::InvalidMove))?;
+                    suffix = rest;
+                }
+                let dir = match suffix {
+                    "" => Dir::CW,
+                    "'" | "’" => Dir::CCW,
+                    "2" | "2'" | "2’" => Dir::HT,
+                    _ => return Err(self.error(offset, end, ParseErrorKind::InvalidMove)),
+                };
+                moves.push(Mov { face, dir });
+            } else {
+                return Err(self.error(offset, offset + c.len_utf8(), ParseErrorKind::UnexpectedCharacter));
+            }
+        }
+        match open_offset {
+            Some(offset) => Err(self.error(offset, offset + 1, ParseErrorKind::UnclosedGroup)),
+            None => Ok((moves, '\0')),
+        }
+    }
+}
+
+impl Moves {
+    pub fn parse(s: &str) -> Result<Self, ParseError> {
+        let mut parser = Parser { input: s, chars: s.char_indices().peekable() };
+        Ok(parser.parse_group(None, &[])?.0)
+    }
+}
@@ -0,0 +1,202 @@
+use std::path::Path;
+
+use crate::context::SolverContext;
+use crate::coord::{self, Coordinate, PhaseDefinition};
+use crate::cube::{self, Cube, Mov};
+use crate::error::Error;
+use crate::profile;
+use crate::prune_table::PruneTable;
+use crate::report::{PhaseReport, SolveReport};
+use crate::solver::{is_redundant, Solver};
+
+/*
+   Korf-style optimal solver.
+   IDA* over all 18 G0_MOVES; the heuristic is the max of several pattern databases,
+   each an exact BFS distance of a projection of the cube, so it never overestimates
+   and the first solution found at a given depth is a shortest one.
+   the databases are all corners (8! permutations, 3^7 orientations) and two halves of the edges
+   (6 edges each, perm(12, 6) positions, 2^6 orientations), as in Korf's solver.
+   with them a 12 move scramble solves in well under a second and a 14 move one in under a minute;
+   each further move multiplies that by roughly ten, so random states, 17 to 20 moves, are out of reach.
+*/
+
+const MAX_DEPTH: usize = 20; // God's number in the half turn metric
+const EDGES_1: [u8; 6] = [0, 1, 2, 3, 4, 5]; // UF UL UB UR FR FL
+const EDGES_2: [u8; 6] = [6, 7, 8, 9, 10, 11]; // BL BR DF DL DB DR
+
+// helper function to get the index of where given cubies are and how they are oriented
+fn get_cubies_position_orientation_index<const N: usize>(permutations: &[u8], orientations: &[u8], cubies: &[u8; N], modulus: u8) -> u32 {
+    let mut positions = [0u8; N];
+    let mut cubie_orientations = [0u8; N];
+    for (position, &p) in permutations.iter().enumerate() {
+        if let Some(i) = cubies.iter().position(|c| *c == p) {
+            positions[i] = position as u8;
+            cubie_orientations[i] = orientations[position];
+        }
+    }
+    let orientation_size = (modulus as u32).pow(N as u32);
+    Solver::permutations_to_index(&positions, permutations.len() as u8) * orientation_size
+        + Solver::orientations_to_index(&cubie_orientations, modulus)
+}
+
+type PatternDatabase<'a> = (&'a PruneTable, fn(Cube) -> u32);
+
+struct OptimalSearch<'a> {
+    tables: [PatternDatabase<'a>; 5],
+    moves: Vec<Mov>,
+    expanded_cnt: u64,
+    pruned_cnt: u64,
+}
+
+impl OptimalSearch<'_> {
+    fn heuristic(&self, cube: Cube) -> usize {
+        self.tables
+            .iter()
+            .map(|(table, fn_get_index)| table.get_depth(fn_get_index(cube)).unwrap_or(0))
+            .max()
+            .unwrap_or(0) as usize
+    }
+
+    // returns true once the cube is solved
+    fn search(&mut self, cube: Cube, depth_left: usize) -> bool {
+        let h = self.heuristic(cube);
+        if h == 0 && Solver::is_solved(cube) {
+            return true;
+        }
+        if h > depth_left {
+            self.pruned_cnt += 1;
+            return false;
+        }
+        if depth_left == 0 {
+            return false;
+        }
+        self.expanded_cnt += 1;
+        let prev_move = self.moves.last().copied();
+        for m in Solver::G0_MOVES {
+            if is_redundant(prev_move, m) {
+                continue;
+            }
+            self.moves.push(m);
+            if self.search(cube.apply_move(m), depth_left - 1) {
+                return true;
+            }
+            self.moves.pop();
+        }
+        false
+    }
+}
+
+impl Solver { // optimal solver
+    pub const OPTIMAL_CORNERS_INDEX_SIZE: u32 = 40320 * 2187; // 8! permutations, 3^7 orientations
+    pub const OPTIMAL_EDGES_INDEX_SIZE: u32 = 665280 * 64; // perm(12, 6) positions, 2^6 orientations
+
+    pub fn is_solved(cube: Cube) -> bool {
+        let solved = Cube::new();
+        cube.corner_orientations == solved.corner_orientations
+            && cube.corner_permutations == solved.corner_permutations
+            && cube.edge_orientations == solved.edge_orientations
+            && cube.edge_permutations == solved.edge_permutations
+    }
+
+    pub fn get_optimal_corners_index(cube: Cube) -> u32 {
+        coord::corner_permutation(cube) * 2187 + coord::corner_orientation(cube) // 2187: 3^7
+    }
+
+    pub fn get_optimal_edges_1_index(cube: Cube) -> u32 {
+        get_cubies_position_orientation_index(&cube.edge_permutations, &cube.edge_orientations, &EDGES_1, 2)
+    }
+
+    pub fn get_optimal_edges_2_index(cube: Cube) -> u32 {
+        get_cubies_position_orientation_index(&cube.edge_permutations, &cube.edge_orientations, &EDGES_2, 2)
+    }
+
+    pub fn solve_optimal(cube: Cube, name: String, print_moves: bool) -> Result<SolveReport, Error> {
+        SolverContext::new().solve_optimal(cube, name, print_moves)
+    }
+
+    pub(crate) fn optimal_corners_definition() -> PhaseDefinition {
+        PhaseDefinition::new(&Self::G0_MOVES, &[coord::CORNER_ORIENTATION, coord::CORNER_PERMUTATION])
+    }
+
+    pub fn gen_prune_table_optimal_corners() -> PruneTable {
+        Self::gen_prune_table("gen_prune_table_optimal_corners".to_string(), &Self::optimal_corners_definition().build())
+    }
+
+    // fn_get_index: get_optimal_edges_1_index or get_optimal_edges_2_index
+    pub(crate) fn optimal_edges_definition(fn_get_index: fn(Cube) -> u32) -> PhaseDefinition {
+        PhaseDefinition::new(&Self::G0_MOVES, &[Coordinate { get: fn_get_index, size: Self::OPTIMAL_EDGES_INDEX_SIZE, flip_bits: 6 }])
+    }
+
+    pub fn gen_prune_table_optimal_edges(fn_get_index: fn(Cube) -> u32) -> PruneTable {
+        Self::gen_prune_table("gen_prune_table_optimal_edges".to_string(), &Self::optimal_edges_definition(fn_get_index).build())
+    }
+}
+
+pub(crate) struct OptimalTables {
+    pub corners: PruneTable,
+    pub edges_1: PruneTable,
+    pub edges_2: PruneTable,
+}
+
+impl OptimalTables {
+    pub fn load(dir: &Path) -> Result<Self, Error> {
+        Ok(Self {
+            corners: PruneTable::load_optimal_corners(dir)?,
+            edges_1: PruneTable::load_optimal_edges_1(dir)?,
+            edges_2: PruneTable::load_optimal_edges_2(dir)?,
+        })
+    }
+}
+
+impl SolverContext { // optimal solver
+    pub fn solve_optimal(&self, cube: Cube, name: String, print_moves: bool) -> Result<SolveReport, Error> {
+        Self::solve_reframed(cube, |frame, cube| {
+            let p = profile::Profile::start(&name, print_moves);
+            let tables = self.optimal_tables()?;
+            // the kociemba phase 1 tables are distances to a subgroup, so they bound the full solve too
+            let kociemba_tables = self.kociemba_tables()?;
+            p.report("prune tables loaded");
+
+            let mut search = OptimalSearch {
+                tables: [
+                    (&tables.corners, Solver::get_optimal_corners_index),
+                    (&tables.edges_1, Solver::get_optimal_edges_1_index),
+                    (&tables.edges_2, Solver::get_optimal_edges_2_index),
+                    (&kociemba_tables.p1_co, Solver::get_kociemba_p1_co_index),
+                    (&kociemba_tables.p1_eo, Solver::get_kociemba_p1_eo_index),
+                ],
+                moves: vec![],
+                expanded_cnt: 0,
+                pruned_cnt: 0,
+            };
+            let mut report = SolveReport::new("optimal", cube);
+            let mut success = false;
+            for depth in search.heuristic(cube)..=MAX_DEPTH {
+                p.report(&format!("searching depth {}, expanded cnt: {}", depth, search.expanded_cnt));
+                if search.search(cube, depth) {
+                    success = true;
+                    break;
+                }
+            }
+            if !success {
+                p.report("no solution found");
+            }
+            let moves = cube::Moves(if success { search.moves } else { vec![] });
+            report.phases.push(PhaseReport {
+                success,
+                moves: moves.clone(),
+                duration: p.elapsed(),
+                expanded_cnt: search.expanded_cnt,
+                pruned_cnt: search.pruned_cnt,
+                ..PhaseReport::new("optimal")
+            });
+            if !success {
+                let duration = p.elapsed();
+                return Err(Error::SearchExhausted(Box::new(report.finish(false, moves, duration))));
+            }
+            let duration = p.end();
+            if print_moves { println!("Optimal solution ({} moves): {}", moves.0.len(), frame.reframe_moves(moves.clone())); }
+            Ok(report.finish(true, moves, duration))
+        })
+    }
+}
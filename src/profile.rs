@@ -9,11 +9,11 @@ pub struct Profile {
 
 impl Profile {
     pub fn start(name: &str, print_enabled: bool) -> Self {
-        return Self {
+        Self {
             name: name.to_string(),
             start_time: Some(Instant::now()),
             print_enabled,
-        };
+        }
     }
 
     pub fn report(&self, message: &str) {
@@ -23,14 +23,20 @@ impl Profile {
         }
     }
 
+    pub fn elapsed(&self) -> Duration {
+        match self.start_time {
+            Some(start_time) => start_time.elapsed(),
+            None => Duration::from_secs(0),
+        }
+    }
+
     pub fn end(self) -> Duration {
         if self.print_enabled {
             self.report("solved");
         }
-        if self.start_time.is_some() {
-            self.start_time.unwrap().elapsed()
-        } else {
-            Duration::from_secs(0)
+        match self.start_time {
+            Some(start_time) => start_time.elapsed(),
+            None => Duration::from_secs(0),
         }
     }
 }
\ No newline at end of file
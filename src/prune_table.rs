@@ -1,28 +1,101 @@
-use std::collections::HashMap;
-use std::fs::File;
-use std::io::{BufWriter, Read, Write};
+use std::env;
+use std::fs::{self, File};
+use std::path::{Path, PathBuf};
+use std::process;
+use std::sync::Arc;
+use std::sync::atomic::{AtomicU32, Ordering};
+use std::time::Instant;
 
-use crate::cube;
+use memmap2::Mmap;
+
+use crate::coord::PhaseDefinition;
+use crate::error::Error;
 use crate::solver;
 
+/*
+   dense prune table: one 4 bit depth per index, two indices per byte (low nibble first).
+   depths saturate at FAR, which also marks indices the generating BFS never reached;
+   both are at least FAR moves from solved, so FAR stays a valid lower bound.
+   file layout, little endian:
+     magic "RBPT" | version: u16 | max depth: u8 | reserved: u8 | size: u32 |
+     definition hash: u64 | checksum: u64 | packed depths
+   the definition hash identifies the moves and coordinates the table was generated for,
+   the checksum covers the packed depths.
+*/
+
+const MAGIC: [u8; 4] = *b"RBPT";
+const VERSION: u16 = 3; // 1: headerless (index: u32, depth: u8) entries, 2: no definition hash or checksum
+const HEADER_LEN: usize = 28;
+const FAR: u8 = 0xF;
+
+// packed depths, either owned or read straight from a read-only mapping of the table file
+#[derive(Clone, Debug)]
+enum Storage {
+    Owned(Vec<u8>),
+    Mapped(Arc<Mmap>), // the whole file, header included
+}
+
 #[derive(Clone, Debug)]
 pub struct PruneTable {
     max_depth: u8,
-    table: HashMap<u32, u8>, // maps index to depth
+    size: u32, // number of indices
+    definition_hash: u64, // PhaseDefinition::hash of the phase the table was generated for
+    table: Storage,
+}
+
+// header fields of a table file
+struct Header {
+    max_depth: u8,
+    size: u32,
+    definition_hash: u64,
 }
 
-pub const PRUNE_TABLE_G1_FILENAME: &str = "prune_tables/thistlethwaite_g1";
-pub const PRUNE_TABLE_G2_FILENAME: &str = "prune_tables/thistlethwaite_g2";
-pub const PRUNE_TABLE_G3_FILENAME: &str = "prune_tables/thistlethwaite_g3";
+// FNV-1a over 8 byte words, then the remaining bytes; every changed bit changes the hash
+pub(crate) fn hash64(bytes: &[u8]) -> u64 {
+    const PRIME: u64 = 0x100000001b3;
+    let mut hash: u64 = 0xcbf29ce484222325;
+    let mut words = bytes.chunks_exact(8);
+    for word in &mut words {
+        hash = (hash ^ u64::from_le_bytes(word.try_into().unwrap())).wrapping_mul(PRIME);
+    }
+    for &byte in words.remainder() {
+        hash = (hash ^ byte as u64).wrapping_mul(PRIME);
+    }
+    hash ^ bytes.len() as u64
+}
+
+pub const PRUNE_TABLE_DIR_ENV: &str = "RUBIKS_SOLVER_TABLE_DIR";
+pub const PRUNE_TABLE_DIR: &str = "prune_tables"; // relative to the working directory, when no cache directory is known
+pub const PRUNE_TABLE_G0_FILENAME: &str = "thistlethwaite_g0";
+pub const PRUNE_TABLE_G1_FILENAME: &str = "thistlethwaite_g1";
+pub const PRUNE_TABLE_G2_FILENAME: &str = "thistlethwaite_g2";
+pub const PRUNE_TABLE_G3_FILENAME: &str = "thistlethwaite_g3";
+pub const PRUNE_TABLE_KOCIEMBA_P1_CO_FILENAME: &str = "kociemba_p1_co";
+pub const PRUNE_TABLE_KOCIEMBA_P1_EO_FILENAME: &str = "kociemba_p1_eo";
+pub const PRUNE_TABLE_KOCIEMBA_P2_CP_FILENAME: &str = "kociemba_p2_cp";
+pub const PRUNE_TABLE_KOCIEMBA_P2_EP_FILENAME: &str = "kociemba_p2_ep";
+pub const PRUNE_TABLE_OPTIMAL_CORNERS_FILENAME: &str = "optimal_corners";
+pub const PRUNE_TABLE_OPTIMAL_EDGES_1_FILENAME: &str = "optimal_edges_1";
+pub const PRUNE_TABLE_OPTIMAL_EDGES_2_FILENAME: &str = "optimal_edges_2";
 
 impl PruneTable {
-    pub fn new() -> Self {
+    pub fn new(size: u32) -> Self {
         Self {
             max_depth: 0,
-            table: HashMap::new(),
+            size,
+            definition_hash: 0,
+            table: Storage::Owned(vec![FAR << 4 | FAR; size.div_ceil(2) as usize]),
         }
     }
 
+    pub fn definition_hash(&self) -> u64 {
+        self.definition_hash
+    }
+
+    pub fn set_definition_hash(&mut self, definition_hash: u64) {
+        self.definition_hash = definition_hash;
+    }
+
     pub fn get_max_depth(&self) -> u8 {
         self.max_depth
     }
@@ -32,66 +105,253 @@ impl PruneTable {
     }
 
     pub fn insert(&mut self, index: u32, depth: u8) {
-        self.table.insert(index, depth);
+        assert!(index < self.size, "prune table index {} out of range {}", index, self.size);
+        let depth = depth.min(FAR);
+        let Storage::Owned(table) = &mut self.table else {
+            panic!("memory mapped prune tables are read-only");
+        };
+        let byte = &mut table[index as usize / 2];
+        *byte = if index.is_multiple_of(2) { (*byte & 0xF0) | depth } else { (*byte & 0x0F) | depth << 4 };
     }
 
-    pub fn get(&self, index: u32) -> Option<u8> {
-        Some(self.max_depth - self.table.get(&index).cloned().unwrap_or(0))
+    // distance from solved as generated, usable as an admissible heuristic
+    pub fn get_depth(&self, index: u32) -> Option<u8> {
+        if index >= self.size {
+            return None;
+        }
+        let byte = self.depths()[index as usize / 2];
+        Some(if index.is_multiple_of(2) { byte & 0x0F } else { byte >> 4 })
     }
 
-    pub fn save(&self, filename: &str) -> Result<(), std::io::Error> {
-        let file = File::create(filename)?;
-        let mut writer = BufWriter::new(file);
-        writer.write_all(&(self.max_depth.to_le_bytes()))?;
-        for (index, depth) in self.table.iter() {
-            writer.write_all(&(index.to_le_bytes()))?;
-            writer.write_all(&(depth.to_le_bytes()))?;
+    fn depths(&self) -> &[u8] {
+        match &self.table {
+            Storage::Owned(table) => table,
+            Storage::Mapped(mmap) => &mmap[HEADER_LEN..],
         }
-        Ok(())
     }
 
-    pub fn load(filename: &str) -> Result<Self, Box<dyn std::error::Error>> {
-        let mut table = Self::new();
-        let mut file = File::open(filename)?;
-        let mut max_depth_bytes: [u8; 1] = [0; 1];
-        file.read_exact(&mut max_depth_bytes)?;
-        table.max_depth = max_depth_bytes[0];
-        let mut mapping_bytes: [u8; 5] = [0; 5];
-        while let Ok(()) = file.read_exact(&mut mapping_bytes) {
-            let index = u32::from_le_bytes(mapping_bytes[..4].try_into()?);
-            let depth = mapping_bytes[4];
-            table.insert(index, depth);
+    pub fn save(&self, path: &Path) -> Result<(), Error> {
+        let mut bytes = Vec::with_capacity(HEADER_LEN + self.depths().len());
+        bytes.extend_from_slice(&MAGIC);
+        bytes.extend_from_slice(&VERSION.to_le_bytes());
+        bytes.push(self.max_depth);
+        bytes.push(0);
+        bytes.extend_from_slice(&self.size.to_le_bytes());
+        bytes.extend_from_slice(&self.definition_hash.to_le_bytes());
+        bytes.extend_from_slice(&hash64(self.depths()).to_le_bytes());
+        bytes.extend_from_slice(self.depths());
+        // replace the file rather than truncating it, which would pull it out from under mapped readers;
+        // the temporary name is unique so concurrent writers of the same table don't interleave
+        static TMP_COUNTER: AtomicU32 = AtomicU32::new(0);
+        let mut tmp_path = path.as_os_str().to_owned();
+        tmp_path.push(format!(".{}.{}.tmp", process::id(), TMP_COUNTER.fetch_add(1, Ordering::Relaxed)));
+        fs::write(&tmp_path, bytes).map_err(|source| Error::Io { path: tmp_path.clone().into(), source })?;
+        fs::rename(&tmp_path, path).map_err(|source| Error::Io { path: path.to_path_buf(), source })
+    }
+
+    // checks the header and checksum of a table file
+    fn parse_header(path: &Path, bytes: &[u8]) -> Result<Header, Error> {
+        let corrupt = |reason: String| Error::CorruptTable { path: path.to_path_buf(), reason };
+        if bytes.len() < HEADER_LEN || bytes[..4] != MAGIC {
+            return Err(corrupt("not a prune table file".to_string()));
+        }
+        let version = u16::from_le_bytes([bytes[4], bytes[5]]);
+        if version != VERSION {
+            return Err(Error::VersionMismatch { path: path.to_path_buf(), version, expected: VERSION });
+        }
+        let u64_at = |offset: usize| u64::from_le_bytes(bytes[offset..offset + 8].try_into().unwrap());
+        let max_depth = bytes[6];
+        let size = u32::from_le_bytes([bytes[8], bytes[9], bytes[10], bytes[11]]);
+        let definition_hash = u64_at(12);
+        let expected = size.div_ceil(2) as usize;
+        if bytes.len() - HEADER_LEN != expected {
+            return Err(corrupt(format!("expected {} bytes of depths, got {}", expected, bytes.len() - HEADER_LEN)));
+        }
+        if hash64(&bytes[HEADER_LEN..]) != u64_at(20) {
+            return Err(corrupt("checksum mismatch".to_string()));
+        }
+        Ok(Header { max_depth, size, definition_hash })
+    }
+
+    // reads the whole table into memory
+    pub fn load(path: &Path) -> Result<Self, Error> {
+        let mut bytes = fs::read(path).map_err(|e| Error::io(path, e))?;
+        let Header { max_depth, size, definition_hash } = Self::parse_header(path, &bytes)?;
+        bytes.drain(..HEADER_LEN);
+        Ok(Self { max_depth, size, definition_hash, table: Storage::Owned(bytes) })
+    }
+
+    /*
+       maps the table file read-only, so every process using the same file shares one copy
+       in the page cache and lookups read the mapped bytes directly.
+       the file must not be modified in place while mapped; save replaces it with a new file
+       instead, and existing mappings keep the old contents.
+    */
+    pub fn map(path: &Path) -> Result<Self, Error> {
+        let file = File::open(path).map_err(|e| Error::io(path, e))?;
+        // SAFETY: table files are only ever replaced by save, never modified in place
+        let mmap = unsafe { Mmap::map(&file).map_err(|e| Error::io(path, e))? };
+        let Header { max_depth, size, definition_hash } = Self::parse_header(path, &mmap)?;
+        Ok(Self { max_depth, size, definition_hash, table: Storage::Mapped(Arc::new(mmap)) })
+    }
+
+    // maps the table, falling back to reading it for filesystems that can't be mapped
+    pub fn open(path: &Path) -> Result<Self, Error> {
+        match Self::map(path) {
+            Err(Error::Io { .. }) => Self::load(path),
+            result => result,
+        }
+    }
+
+    /*
+       directory tables are looked up in and generated into when no directory is given:
+       $RUBIKS_SOLVER_TABLE_DIR, else rubiks-solver in $XDG_CACHE_HOME or ~/.cache,
+       else PRUNE_TABLE_DIR
+    */
+    pub fn default_dir() -> PathBuf {
+        let var = |name| env::var_os(name).filter(|value| !value.is_empty()).map(PathBuf::from);
+        if let Some(dir) = var(PRUNE_TABLE_DIR_ENV) {
+            return dir;
+        }
+        match var("XDG_CACHE_HOME").or_else(|| var("HOME").map(|home| home.join(".cache"))) {
+            Some(cache) => cache.join("rubiks-solver"),
+            None => PathBuf::from(PRUNE_TABLE_DIR),
+        }
+    }
+
+    // opens the table at path and checks it was generated for definition
+    fn open_checked(path: &Path, definition: &PhaseDefinition) -> Result<Self, Error> {
+        let table = Self::open(path)?;
+        let expected_size = definition.coordinates.iter().map(|c| c.size).product::<u32>();
+        if table.definition_hash != definition.hash() || table.size != expected_size {
+            return Err(Error::DefinitionMismatch { path: path.to_path_buf() });
         }
         Ok(table)
     }
 
-    pub fn gen_g1() {
-        let cube = cube::Cube::new();
-        let table = solver::Solver::gen_prune_table_g1(cube);
-        let _ = table.save(PRUNE_TABLE_G1_FILENAME);
+    /*
+       opens dir/filename, generating and saving the table first if there is none yet,
+       or if the one there is corrupt, of another file version or for another definition
+    */
+    fn open_or_generate(dir: &Path, filename: &str, definition: &PhaseDefinition, generate: impl FnOnce() -> PruneTable) -> Result<Self, Error> {
+        let path = dir.join(filename);
+        match Self::open_checked(&path, definition) {
+            Err(Error::MissingTable { .. }) => eprintln!("Generating prune table {}", path.display()),
+            Err(Error::CorruptTable { reason, .. }) => eprintln!("Regenerating prune table {}, it is corrupt ({})", path.display(), reason),
+            Err(Error::VersionMismatch { version, .. }) => eprintln!("Regenerating prune table {}, it has version {}", path.display(), version),
+            Err(Error::DefinitionMismatch { .. }) => eprintln!("Regenerating prune table {}, it was generated for different moves or coordinates", path.display()),
+            result => return result,
+        }
+        let start = Instant::now();
+        fs::create_dir_all(dir).map_err(|source| Error::Io { path: dir.to_path_buf(), source })?;
+        generate().save(&path)?;
+        eprintln!("Generated prune table {} in {:.3?}", path.display(), start.elapsed());
+        Self::open_checked(&path, definition)
     }
 
-    pub fn load_g1() -> Self {
-        Self::load(PRUNE_TABLE_G1_FILENAME).unwrap()
+    // every table file the solvers use, with the definition it must match
+    fn definitions() -> Vec<(&'static str, PhaseDefinition)> {
+        use solver::Solver;
+        vec![
+            (PRUNE_TABLE_G0_FILENAME, Solver::g0_definition()),
+            (PRUNE_TABLE_G1_FILENAME, Solver::g1_definition()),
+            (PRUNE_TABLE_G2_FILENAME, Solver::g2_definition()),
+            (PRUNE_TABLE_G3_FILENAME, Solver::g3_definition()),
+            (PRUNE_TABLE_KOCIEMBA_P1_CO_FILENAME, Solver::kociemba_p1_co_definition()),
+            (PRUNE_TABLE_KOCIEMBA_P1_EO_FILENAME, Solver::kociemba_p1_eo_definition()),
+            (PRUNE_TABLE_KOCIEMBA_P2_CP_FILENAME, Solver::kociemba_p2_cp_definition()),
+            (PRUNE_TABLE_KOCIEMBA_P2_EP_FILENAME, Solver::kociemba_p2_ep_definition()),
+            (PRUNE_TABLE_OPTIMAL_CORNERS_FILENAME, Solver::optimal_corners_definition()),
+            (PRUNE_TABLE_OPTIMAL_EDGES_1_FILENAME, Solver::optimal_edges_definition(Solver::get_optimal_edges_1_index)),
+            (PRUNE_TABLE_OPTIMAL_EDGES_2_FILENAME, Solver::optimal_edges_definition(Solver::get_optimal_edges_2_index)),
+        ]
     }
 
-    pub fn gen_g2() {
-        let cube = cube::Cube::new();
-        let table = solver::Solver::gen_prune_table_g2(cube);
-        let _ = table.save(PRUNE_TABLE_G2_FILENAME);
+    // opens and checks every table in dir without generating missing ones
+    pub fn verify_all(dir: &Path) -> Vec<(PathBuf, Result<Self, Error>)> {
+        Self::definitions().into_iter().map(|(filename, definition)| {
+            let path = dir.join(filename);
+            let result = Self::open_checked(&path, &definition);
+            (path, result)
+        }).collect()
     }
 
-    pub fn load_g2() -> Self {
-        Self::load(PRUNE_TABLE_G2_FILENAME).unwrap()
+    pub fn gen_g0(dir: &Path) -> Result<(), Error> {
+        solver::Solver::gen_prune_table_g0().save(&dir.join(PRUNE_TABLE_G0_FILENAME))
+    }
+
+    pub fn load_g0(dir: &Path) -> Result<Self, Error> {
+        Self::open_or_generate(dir, PRUNE_TABLE_G0_FILENAME, &solver::Solver::g0_definition(), solver::Solver::gen_prune_table_g0)
+    }
+
+    pub fn gen_g1(dir: &Path) -> Result<(), Error> {
+        solver::Solver::gen_prune_table_g1().save(&dir.join(PRUNE_TABLE_G1_FILENAME))
+    }
+
+    pub fn load_g1(dir: &Path) -> Result<Self, Error> {
+        Self::open_or_generate(dir, PRUNE_TABLE_G1_FILENAME, &solver::Solver::g1_definition(), solver::Solver::gen_prune_table_g1)
+    }
+
+    pub fn gen_g2(dir: &Path) -> Result<(), Error> {
+        solver::Solver::gen_prune_table_g2().save(&dir.join(PRUNE_TABLE_G2_FILENAME))
+    }
+
+    pub fn load_g2(dir: &Path) -> Result<Self, Error> {
+        Self::open_or_generate(dir, PRUNE_TABLE_G2_FILENAME, &solver::Solver::g2_definition(), solver::Solver::gen_prune_table_g2)
     }
     
-    pub fn gen_g3() {
-        let cube = cube::Cube::new();
-        let table = solver::Solver::gen_prune_table_g3(cube);
-        let _ = table.save(PRUNE_TABLE_G3_FILENAME);
+    pub fn gen_g3(dir: &Path) -> Result<(), Error> {
+        solver::Solver::gen_prune_table_g3().save(&dir.join(PRUNE_TABLE_G3_FILENAME))
+    }
+
+    pub fn load_g3(dir: &Path) -> Result<Self, Error> {
+        Self::open_or_generate(dir, PRUNE_TABLE_G3_FILENAME, &solver::Solver::g3_definition(), solver::Solver::gen_prune_table_g3)
+    }
+
+    pub fn gen_kociemba(dir: &Path) -> Result<(), Error> {
+        solver::Solver::gen_prune_table_kociemba_p1_co().save(&dir.join(PRUNE_TABLE_KOCIEMBA_P1_CO_FILENAME))?;
+        solver::Solver::gen_prune_table_kociemba_p1_eo().save(&dir.join(PRUNE_TABLE_KOCIEMBA_P1_EO_FILENAME))?;
+        solver::Solver::gen_prune_table_kociemba_p2_cp().save(&dir.join(PRUNE_TABLE_KOCIEMBA_P2_CP_FILENAME))?;
+        solver::Solver::gen_prune_table_kociemba_p2_ep().save(&dir.join(PRUNE_TABLE_KOCIEMBA_P2_EP_FILENAME))?;
+        Ok(())
+    }
+
+    pub fn load_kociemba_p1_co(dir: &Path) -> Result<Self, Error> {
+        Self::open_or_generate(dir, PRUNE_TABLE_KOCIEMBA_P1_CO_FILENAME, &solver::Solver::kociemba_p1_co_definition(), solver::Solver::gen_prune_table_kociemba_p1_co)
+    }
+
+    pub fn load_kociemba_p1_eo(dir: &Path) -> Result<Self, Error> {
+        Self::open_or_generate(dir, PRUNE_TABLE_KOCIEMBA_P1_EO_FILENAME, &solver::Solver::kociemba_p1_eo_definition(), solver::Solver::gen_prune_table_kociemba_p1_eo)
+    }
+
+    pub fn load_kociemba_p2_cp(dir: &Path) -> Result<Self, Error> {
+        Self::open_or_generate(dir, PRUNE_TABLE_KOCIEMBA_P2_CP_FILENAME, &solver::Solver::kociemba_p2_cp_definition(), solver::Solver::gen_prune_table_kociemba_p2_cp)
+    }
+
+    pub fn load_kociemba_p2_ep(dir: &Path) -> Result<Self, Error> {
+        Self::open_or_generate(dir, PRUNE_TABLE_KOCIEMBA_P2_EP_FILENAME, &solver::Solver::kociemba_p2_ep_definition(), solver::Solver::gen_prune_table_kociemba_p2_ep)
+    }
+
+    pub fn gen_optimal(dir: &Path) -> Result<(), Error> {
+        solver::Solver::gen_prune_table_optimal_corners().save(&dir.join(PRUNE_TABLE_OPTIMAL_CORNERS_FILENAME))?;
+        solver::Solver::gen_prune_table_optimal_edges(solver::Solver::get_optimal_edges_1_index).save(&dir.join(PRUNE_TABLE_OPTIMAL_EDGES_1_FILENAME))?;
+        solver::Solver::gen_prune_table_optimal_edges(solver::Solver::get_optimal_edges_2_index).save(&dir.join(PRUNE_TABLE_OPTIMAL_EDGES_2_FILENAME))?;
+        Ok(())
+    }
+
+    pub fn load_optimal_corners(dir: &Path) -> Result<Self, Error> {
+        Self::open_or_generate(dir, PRUNE_TABLE_OPTIMAL_CORNERS_FILENAME, &solver::Solver::optimal_corners_definition(), solver::Solver::gen_prune_table_optimal_corners)
+    }
+
+    pub fn load_optimal_edges_1(dir: &Path) -> Result<Self, Error> {
+        Self::open_or_generate(dir, PRUNE_TABLE_OPTIMAL_EDGES_1_FILENAME, &solver::Solver::optimal_edges_definition(solver::Solver::get_optimal_edges_1_index), || {
+            solver::Solver::gen_prune_table_optimal_edges(solver::Solver::get_optimal_edges_1_index)
+        })
     }
 
-    pub fn load_g3() -> Self {
-        Self::load(PRUNE_TABLE_G3_FILENAME).unwrap()
+    pub fn load_optimal_edges_2(dir: &Path) -> Result<Self, Error> {
+        Self::open_or_generate(dir, PRUNE_TABLE_OPTIMAL_EDGES_2_FILENAME, &solver::Solver::optimal_edges_definition(solver::Solver::get_optimal_edges_2_index), || {
+            solver::Solver::gen_prune_table_optimal_edges(solver::Solver::get_optimal_edges_2_index)
+        })
     }
 }
\ No newline at end of file
@@ -0,0 +1,93 @@
+use std::time::Duration;
+
+use serde::{Serialize, Serializer};
+
+use crate::cube::{Cube, Moves};
+
+/*
+   structured results of a solve, for callers that want more than the printed output.
+   a SolveReport holds the solution and one PhaseReport per search the solver ran,
+   e.g. the four Thistlethwaite phases; it serializes to JSON with moves in notation
+   and durations in milliseconds.
+*/
+
+#[derive(Clone, Debug, Serialize)]
+pub struct PhaseReport {
+    pub name: String,
+    pub success: bool,
+    pub moves: Moves,
+    #[serde(rename = "duration_ms", serialize_with = "serialize_millis")]
+    pub duration: Duration,
+    pub expanded_cnt: u64, // nodes whose successors were generated
+    pub pruned_cnt: u64, // nodes cut off by a prune table
+}
+
+impl PhaseReport {
+    pub fn new(name: &str) -> Self {
+        Self {
+            name: name.to_string(),
+            success: false,
+            moves: Moves(vec![]),
+            duration: Duration::ZERO,
+            expanded_cnt: 0,
+            pruned_cnt: 0,
+        }
+    }
+}
+
+#[derive(Clone, Debug, Serialize)]
+pub struct SolveReport {
+    pub solver: String,
+    pub facelets: String, // input state as the cube is held, with its centers where they were turned to
+    pub success: bool,
+    pub solution: Moves,
+    pub length_htm: usize,
+    pub length_qtm: usize,
+    #[serde(rename = "duration_ms", serialize_with = "serialize_millis")]
+    pub duration: Duration,
+    pub phases: Vec<PhaseReport>,
+}
+
+impl SolveReport {
+    pub fn new(solver: &str, cube: Cube) -> Self {
+        Self {
+            solver: solver.to_string(),
+            facelets: cube.to_facelets(),
+            success: false,
+            solution: Moves(vec![]),
+            length_htm: 0,
+            length_qtm: 0,
+            duration: Duration::ZERO,
+            phases: vec![],
+        }
+    }
+
+    pub fn finish(mut self, success: bool, solution: Moves, duration: Duration) -> Self {
+        self.success = success;
+        self.length_htm = solution.htm_length();
+        self.length_qtm = solution.qtm_length();
+        self.solution = solution;
+        self.duration = duration;
+        self
+    }
+
+    // the report of a solve of cube.reset_centers(), restated for cube itself
+    pub fn reframe(mut self, cube: Cube) -> Self {
+        self.facelets = cube.to_facelets();
+        self.solution = cube.reframe_moves(self.solution);
+        for phase in &mut self.phases {
+            phase.moves = cube.reframe_moves(std::mem::replace(&mut phase.moves, Moves(vec![])));
+        }
+        self
+    }
+}
+
+impl Serialize for Moves {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.collect_str(self)
+    }
+}
+
+pub fn serialize_millis<S: Serializer>(duration: &Duration, serializer: S) -> Result<S::Ok, S::Error> {
+    serializer.serialize_f64(duration.as_secs_f64() * 1000.0)
+}
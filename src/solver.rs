@@ -1,32 +1,45 @@
-use std::collections::{HashSet, VecDeque};
-
+#[cfg(test)]
+use std::collections::VecDeque;
+use std::path::Path;
+use std::sync::atomic::{AtomicU8, Ordering};
+use std::thread;
+use std::time::Instant;
+
+use crate::batch;
+use crate::context::SolverContext;
+use crate::coord::{self, Phase, PhaseDefinition};
+use crate::error::Error;
 use crate::cube::{self, U, UP, U2, D, DP, D2, L, LP, L2, R, RP, R2, F, FP, F2, B, BP, B2};
 use crate::profile;
 use crate::prune_table::PruneTable;
+use crate::report::{PhaseReport, SolveReport};
 
-#[derive(Copy, Clone, Eq, PartialEq, Hash, Debug)]
-pub enum SolveMode {
-    Bfs,
-    PruneGen,
-    Prune,
-}
+const MAX_IDA_STAR_DEPTH: u8 = 20; // bound for searches without a prune table
 
 const FACTORIALS: [u32; 13] = [1, 1, 2, 6, 24, 120, 720, 5040, 40320, 362880, 3628800, 39916800, 479001600];
 
-pub fn comb(n: u32, r: u32) -> u32 {
+pub(crate) fn comb(n: u32, r: u32) -> u32 {
     if n < r {
         return 0;
     } 
     FACTORIALS[n as usize] / (FACTORIALS[r as usize] * FACTORIALS[(n - r) as usize])
 }
 
-pub fn perm(n: u32, r: u32) -> u32 {
+pub(crate) fn perm(n: u32, r: u32) -> u32 {
     if n < r {
         return 0;
     } 
     FACTORIALS[n as usize] / FACTORIALS[(n - r) as usize]
 }
 
+// skip moves on the same face, and fix the order of moves on opposite faces since they commute
+pub(crate) fn is_redundant(prev_move: Option<cube::Mov>, mov: cube::Mov) -> bool {
+    match prev_move {
+        Some(prev) => prev.face == mov.face || (prev.face.axis() == mov.face.axis() && (mov.face as u8) < (prev.face as u8)),
+        None => false,
+    }
+}
+
 pub struct Solver;
 
 impl Solver { // Thistlethwaite solver
@@ -35,104 +48,194 @@ impl Solver { // Thistlethwaite solver
     pub const G2_MOVES: [cube::Mov; 10] = [U2, D2, L, LP, L2, R, RP, R2, F2, B2];
     pub const G3_MOVES: [cube::Mov; 6] = [U2, D2, L2, R2, F2, B2];
 
-    pub fn solve_group(
-        name: String,
-        mode: SolveMode,
-        cube: cube::Cube,
-        is_solved: Option<fn(cube::Cube) -> bool>,
-        fn_get_index: fn(cube::Cube) -> u32,
-        moves: &[cube::Mov],
-        mut prune_table: Option<&mut PruneTable>,
-    ) -> (bool, cube::Moves) {
+    // iterative deepening DFS, with the prune table depths as heuristic when given
+    pub(crate) fn solve_group(name: String, cube: cube::Cube, phase: &Phase, prune_table: Option<&PruneTable>) -> PhaseReport {
         let p = profile::Profile::start(&name, false);
-        if is_solved.is_some() && is_solved.unwrap()(cube) {
+        let mut report = PhaseReport::new(&name);
+        let Some(index) = phase.get_index(cube) else {
+            p.report("cube is outside the group");
+            report.duration = p.elapsed();
+            return report;
+        };
+        if index == phase.solved_index {
             p.report("already solved");
-            return (true, cube::Moves(vec![]));
+            report.success = true;
+            report.duration = p.elapsed();
+            return report;
         }
 
-        let mut queue = VecDeque::from([(cube, cube::Moves(vec![]))]);
-        let mut visited_indices = HashSet::from([]);
-        let mut pruned_cnt = 0;
+        Self::solve_group_ida_star(&p, &mut report, cube, index, phase, prune_table);
+        report.duration = p.elapsed();
+        report
+    }
 
-        while let Some((current_cube, current_moves)) = queue.pop_front() {
-            if mode != SolveMode::PruneGen && is_solved.is_some() && is_solved.unwrap()(current_cube) {
-                if mode == SolveMode::Prune {
-                    p.report(&format!("solved, pruned cnt: {}", pruned_cnt));
-                } else {
-                    p.end();
+    // fills in the moves, success and node counts of report
+    fn solve_group_ida_star(p: &profile::Profile, report: &mut PhaseReport, cube: cube::Cube, index: u32, phase: &Phase, prune_table: Option<&PruneTable>) {
+        let heuristic = |index| prune_table.and_then(|table| table.get_depth(index)).unwrap_or(0);
+        let max_depth = prune_table.map_or(MAX_IDA_STAR_DEPTH, |table| table.get_max_depth().max(heuristic(index)));
+        let mut moves = Vec::with_capacity(max_depth as usize);
+
+        // returns true once solved, with the solution left in moves
+        fn search(
+            phase: &Phase,
+            heuristic: &dyn Fn(u32) -> u8,
+            index: u32,
+            depth_left: u8,
+            prev_move: Option<cube::Mov>,
+            moves: &mut Vec<cube::Mov>,
+            report: &mut PhaseReport,
+        ) -> bool {
+            if index == phase.solved_index {
+                return true;
+            }
+            if depth_left == 0 {
+                return false;
+            }
+            if heuristic(index) > depth_left {
+                report.pruned_cnt += 1;
+                return false;
+            }
+            report.expanded_cnt += 1;
+            for (i, m) in phase.moves.iter().enumerate() {
+                if prev_move.is_some_and(|prev| prev.face == m.face) {
+                    continue; // skip next move of the same face
+                }
+                moves.push(*m);
+                if search(phase, heuristic, phase.apply(index, i), depth_left - 1, Some(*m), moves, report) {
+                    return true;
                 }
-                return (true, current_moves);
+                moves.pop();
+            }
+            false
+        }
+
+        for depth in heuristic(index)..=max_depth {
+            if search(phase, &heuristic, index, depth, cube.prev_move, &mut moves, report) {
+                p.report(&format!("solved at depth {}, expanded cnt: {}", depth, report.expanded_cnt));
+                report.success = true;
+                report.moves = cube::Moves(moves);
+                return;
             }
-            let current_index = fn_get_index(current_cube);
-            if visited_indices.contains(&current_index) {
+        }
+        p.report(&format!("no solution found, expanded cnt: {}", report.expanded_cnt));
+    }
+
+    pub(crate) fn gen_prune_table(name: String, phase: &Phase) -> PruneTable {
+        let threads = thread::available_parallelism().map_or(1, |n| n.get());
+        Self::gen_prune_table_parallel(name, phase, threads)
+    }
+
+    // BFS over every state reachable from solved with the phase's moves, recording the depth of each index.
+    // kept as the reference the parallel generation is tested against
+    #[cfg(test)]
+    pub(crate) fn gen_prune_table_sequential(name: String, phase: &Phase) -> PruneTable {
+        let p = profile::Profile::start(&name, false);
+        let mut table = PruneTable::new(phase.size);
+        table.set_definition_hash(phase.definition_hash);
+        let mut queue = VecDeque::from([(phase.solved_index, 0u8, None)]);
+        let mut visited = vec![false; phase.size as usize];
+        let mut visited_cnt = 0;
+
+        while let Some((current_index, depth, prev_move)) = queue.pop_front() {
+            if visited[current_index as usize] {
                 continue;
             }
-            if mode == SolveMode::Prune { // prune
-                if let Some(table) = prune_table.as_mut() {
-                    if let Some(depth) = table.get(current_index) {
-                        if current_moves.0.len() as u8 >= depth {
-                            pruned_cnt += 1;
-                            continue;
-                        }
-                    }
-                }
+            visited[current_index as usize] = true;
+            visited_cnt += 1;
+            table.insert(current_index, depth);
+            if depth > table.get_max_depth() {
+                table.set_max_depth(depth);
+                p.report(&format!("max depth: {}", depth));
             }
-            visited_indices.insert(current_index);
-
-            if mode == SolveMode::PruneGen { // prune generation
-                if let Some(table) = prune_table.as_mut() {
-                    table.insert(current_index, current_moves.0.len() as u8);
-                    if current_moves.0.len() as u8 > table.get_max_depth() {
-                        table.set_max_depth(current_moves.0.len() as u8);
-                        p.report(&format!("max depth: {}", table.get_max_depth()));
-                    }
+
+            for (i, m) in phase.moves.iter().enumerate() {
+                if prev_move.is_some_and(|prev: cube::Mov| prev.face == m.face) {
+                    continue; // skip next move of the same face
                 }
+                queue.push_back((phase.apply(current_index, i), depth + 1, Some(*m)));
             }
+        }
 
-            for m in moves {
-                if current_cube.prev_move.is_some() && current_cube.prev_move.unwrap().face == m.face {
-                    continue; // skip next move of the same face
+        p.report(&format!("prune table generated, visited states len: {}", visited_cnt));
+        table
+    }
+
+    /*
+       the same BFS one layer at a time: the frontier is split into chunks that the threads expand,
+       claiming each unvisited successor with a compare-exchange so it joins exactly one next frontier.
+       whichever thread claims an index, its depth is its distance from solved, so the table is
+       identical to the sequential one. each layer's size is reported to stderr as it completes,
+       the large tables take minutes.
+    */
+    pub(crate) fn gen_prune_table_parallel(name: String, phase: &Phase, threads: usize) -> PruneTable {
+        const CHUNK_LEN: usize = 1 << 14;
+        const UNVISITED: u8 = u8::MAX;
+        let start = Instant::now();
+        let depths: Vec<AtomicU8> = (0..phase.size).map(|_| AtomicU8::new(UNVISITED)).collect();
+        depths[phase.solved_index as usize].store(0, Ordering::Relaxed);
+        let mut frontier = vec![phase.solved_index];
+        let mut depth = 0u8;
+        let mut visited_cnt = 0;
+
+        loop {
+            visited_cnt += frontier.len();
+            let chunks: Vec<&[u32]> = frontier.chunks(CHUNK_LEN).collect();
+            let next_frontier = batch::run(chunks.len(), threads, |i| {
+                let mut next = vec![];
+                for &index in chunks[i] {
+                    for move_index in 0..phase.moves.len() {
+                        let next_index = phase.apply(index, move_index);
+                        if depths[next_index as usize].compare_exchange(UNVISITED, depth + 1, Ordering::Relaxed, Ordering::Relaxed).is_ok() {
+                            next.push(next_index);
+                        }
+                    }
                 }
-                let new_cube = current_cube.apply_move(*m);
-                let mut new_moves = current_moves.clone();
-                new_moves.push(*m);
-                queue.push_back((new_cube, new_moves));
+                next
+            }).concat();
+            if next_frontier.is_empty() {
+                break;
             }
+            frontier = next_frontier;
+            depth += 1;
+            eprintln!("[{}] depth {}: {} states in {:.3?}", name, depth, frontier.len(), start.elapsed());
         }
 
-        if mode == SolveMode::PruneGen {
-            p.report(&format!("prune table generated, visited states len: {}", visited_indices.len()));
-        } else {
-            p.report(&format!("no solution found, visited states len: {}", visited_indices.len()));
+        let mut table = PruneTable::new(phase.size);
+        table.set_definition_hash(phase.definition_hash);
+        table.set_max_depth(depth);
+        for (index, d) in depths.into_iter().map(AtomicU8::into_inner).enumerate() {
+            if d != UNVISITED {
+                table.insert(index as u32, d);
+            }
         }
-        return (false, cube::Moves(vec![]));
+        eprintln!("[{}] {} of {} states reached in {:.3?}", name, visited_cnt, phase.size, start.elapsed());
+        table
     }
 
-    pub fn orientations_to_index(orientations: &[u8], modulus: u8) -> u32 {
+    pub(crate) fn orientations_to_index(orientations: &[u8], modulus: u8) -> u32 {
         let mut index = 0;
-        for i in 0..orientations.len() {
-            index = index * modulus as u32 + orientations[i] as u32;
+        for orientation in orientations {
+            index = index * modulus as u32 + *orientation as u32;
         }
         index
     }
 
-    pub fn combinations_to_index(combinations: &[u8]) -> u32 {
+    pub(crate) fn combinations_to_index(combinations: &[u8]) -> u32 {
         let mut c: Vec<u8> = combinations.to_vec();
         c.sort();
         let mut index = 0;
         for i in (0..c.len()).rev() {
-            index += comb(c[i] as u32, (i + 1) as u32) as u32;
+            index += comb(c[i] as u32, (i + 1) as u32);
         }
         index
     }
 
-    pub fn permutations_to_index(permutations: &[u8], n: u8) -> u32 { // n: size of all allowed elements
+    pub(crate) fn permutations_to_index(permutations: &[u8], n: u8) -> u32 { // n: size of all allowed elements
         let k = permutations.len() as u8;
         let mut index: u32 = 0;
-        let mut used = vec![false; 12];
+        let mut used = [false; 12];
 
-        for i in 0..k as usize {
-            let p = permutations[i];
+        for (i, &p) in permutations.iter().enumerate() {
             let mut count = 0u32;
             for j in 0..p {
                 if !used[j as usize] {
@@ -144,182 +247,218 @@ impl Solver { // Thistlethwaite solver
             let multiplier = if still_to_choose == 0 {
                 1
             } else {
-                perm(remaining_after_this as u32, still_to_choose as u32) as u32
+                perm(remaining_after_this as u32, still_to_choose as u32)
             };
-            index += count * multiplier as u32;
+            index += count * multiplier;
             used[p as usize] = true;
         }
         index
     }
 
     // helper function to get the combination index of cubie positions in permutation
-    pub fn get_cubies_position_index<const N: usize>(permutations: &[u8], cubies: &[u8; N]) -> u32 {
+    pub(crate) fn get_cubies_position_index<const N: usize>(permutations: &[u8], cubies: &[u8; N]) -> u32 {
         let mut perm = [0u8; N];
         let mut i = 0;
-        for position in 0..permutations.len() {
+        for (position, &p) in permutations.iter().enumerate() {
             for cubie in cubies {
-                if permutations[position] == *cubie {
+                if p == *cubie {
                     perm[i] = position as u8;
                     i += 1;
                     break;
                 }
             }
         }
-        Self::combinations_to_index(&perm) as u32
+        Self::combinations_to_index(&perm)
     }
 
-    // helper function to get the permutation index on given positions in permutation
-    fn get_cube_permutation_index_at_position(permutations: &[u8], positions: [u8; 4]) -> u32 {
-        let mut perm = [0; 4];
-        for i in 0..4 {
-            perm[i] = permutations[positions[i] as usize];
+    // helper function to get the permutation index of cubies on their own positions, relabeled to 0..N
+    pub(crate) fn get_relabeled_permutation_index<const N: usize>(permutations: &[u8], cubies: &[u8; N]) -> u32 {
+        let mut perm = [0u8; N];
+        for (i, position) in cubies.iter().enumerate() {
+            let cubie = permutations[*position as usize];
+            perm[i] = cubies.iter().position(|c| *c == cubie).unwrap_or(0) as u8;
         }
-        Self::permutations_to_index(&perm, 8)
+        Self::permutations_to_index(&perm, N as u8)
     }
 
-    fn is_solved_g0(cube: cube::Cube) -> bool { // edge orientations are all 0
-        cube.edge_orientations == [0; 12]
+    pub(crate) fn g0_definition() -> PhaseDefinition { // edge orientations are all 0
+        PhaseDefinition::new(&Self::G0_MOVES, &[coord::EDGE_ORIENTATION])
     }
 
-    fn get_g0_index(cube: cube::Cube) -> u32 {
-        Self::orientations_to_index(&cube.edge_orientations, 2) as u32
+    pub(crate) fn g0_phase() -> Phase {
+        Self::g0_definition().build()
     }
 
-    pub fn solve_g0(cube: cube::Cube) -> (bool, cube::Moves) {
-        Self::solve_group("solve_g0".to_string(), SolveMode::Bfs, cube, Some(Self::is_solved_g0), Self::get_g0_index, &Self::G0_MOVES, None)
+    pub(crate) fn solve_g0(cube: cube::Cube, phase: &Phase, prune_table: &PruneTable) -> PhaseReport {
+        Self::solve_group("g0".to_string(), cube, phase, Some(prune_table))
     }
 
-    pub fn get_g1_index(cube: cube::Cube) -> u32 {
-        let corner_orientation_index = Self::orientations_to_index(&cube.corner_orientations, 3);
-        let lr_slice_combination_index = Self::get_cubies_position_index(&cube.edge_permutations, &cube::Cube::LR_SLICE_EDGES);
-        corner_orientation_index as u32 * 495 + lr_slice_combination_index // 495: comb(12, 4)
+    pub(crate) fn get_g1_index(cube: cube::Cube) -> u32 {
+        coord::corner_orientation(cube) * 495 + coord::lr_slice_combination(cube) // 495: comb(12, 4)
     }
 
-    fn is_solved_g1(cube: cube::Cube) -> bool { // corner orientations are all 0; LR mid slice combination match
-        let g1_index = Self::get_g1_index(cube);
-        g1_index == 267 // combination index of [0, 2, 8, 10]
+    pub(crate) fn g1_definition() -> PhaseDefinition { // corner orientations are all 0; LR mid slice combination match
+        PhaseDefinition::new(&Self::G1_MOVES, &[coord::LR_SLICE_COMBINATION, coord::CORNER_ORIENTATION])
     }
 
-    pub fn solve_g1(cube: cube::Cube) -> (bool, cube::Moves) {
-        let mut prune_table = PruneTable::load_g1();
-        Self::solve_group("solve_g1".to_string(), SolveMode::Prune, cube, Some(Self::is_solved_g1), Self::get_g1_index, &Self::G1_MOVES, Some(&mut prune_table))
+    pub(crate) fn g1_phase() -> Phase {
+        Self::g1_definition().build()
     }
 
-    pub fn get_g2_index(cube: cube::Cube) -> u32 {
-        let ud_slice_comb_index = Self::get_cubies_position_index(&cube.edge_permutations, &cube::Cube::UD_SLICE_EDGES);
-        let ht1_i = Self::get_cubies_position_index(&cube.corner_permutations, &cube::Cube::HALF_TETRAD_1_CORNERS);
-        let ht2_i = Self::get_cubies_position_index(&cube.corner_permutations, &cube::Cube::HALF_TETRAD_2_CORNERS);
-        let ht3_i = Self::get_cubies_position_index(&cube.corner_permutations, &cube::Cube::HALF_TETRAD_3_CORNERS);
+    pub(crate) fn solve_g1(cube: cube::Cube, phase: &Phase, prune_table: &PruneTable) -> PhaseReport {
+        Self::solve_group("g1".to_string(), cube, phase, Some(prune_table))
+    }
+
+    pub(crate) fn get_g2_index(cube: cube::Cube) -> u32 {
+        let ud_slice_comb_index = coord::ud_slice_combination(cube);
+        let ht1_i = coord::half_tetrad_1_combination(cube);
+        let ht2_i = coord::half_tetrad_2_combination(cube);
+        let ht3_i = coord::half_tetrad_3_combination(cube);
         let ht_size = comb(8, 2);
-        return ht1_i + ht_size*(ht2_i + ht_size*(ht3_i + ht_size*ud_slice_comb_index));
+        ht1_i + ht_size*(ht2_i + ht_size*(ht3_i + ht_size*ud_slice_comb_index))
     }
 
-    fn is_solved_g2(cube: cube::Cube) -> bool { // first, second & third half-tetrad combination match, ud mid slice combination match
-        Self::get_g2_index(cube) == 1518553
+    pub(crate) fn g2_definition() -> PhaseDefinition { // first, second & third half-tetrad combination match, ud mid slice combination match
+        PhaseDefinition::new(&Self::G2_MOVES, &[
+            coord::HALF_TETRAD_1_COMBINATION,
+            coord::HALF_TETRAD_2_COMBINATION,
+            coord::HALF_TETRAD_3_COMBINATION,
+            coord::UD_SLICE_COMBINATION,
+        ])
     }
 
-    pub fn solve_g2(cube: cube::Cube) -> (bool, cube::Moves) {
-        let mut prune_table = PruneTable::load_g2();
-        Self::solve_group("solve_g2".to_string(), SolveMode::Prune, cube, Some(Self::is_solved_g2), Self::get_g2_index, &Self::G2_MOVES, Some(&mut prune_table))
+    pub(crate) fn g2_phase() -> Phase {
+        Self::g2_definition().build()
     }
 
-    pub fn get_g3_index(cube: cube::Cube) -> u32 {
-        let e1_index = Self::get_cube_permutation_index_at_position(&cube.edge_permutations, cube::Cube::LR_SLICE_EDGES);
-        let e2_index = Self::get_cube_permutation_index_at_position(&cube.edge_permutations, cube::Cube::UD_SLICE_EDGES);
-        let e3_index = Self::get_cube_permutation_index_at_position(&cube.edge_permutations, cube::Cube::FB_SLICE_EDGES);
-        let c1_index = Self::get_cube_permutation_index_at_position(&cube.corner_permutations, cube::Cube::TETRAD_1_CORNERS);
-        let c2_index = Self::get_cube_permutation_index_at_position(&cube.corner_permutations, cube::Cube::TETRAD_2_CORNERS);
+    pub(crate) fn solve_g2(cube: cube::Cube, phase: &Phase, prune_table: &PruneTable) -> PhaseReport {
+        Self::solve_group("g2".to_string(), cube, phase, Some(prune_table))
+    }
+
+    pub(crate) fn get_g3_index(cube: cube::Cube) -> u32 { // in G3 every slice and tetrad keeps its own cubies
+        let e1_index = coord::lr_slice_permutation(cube);
+        let e2_index = coord::ud_slice_permutation(cube);
+        let e3_index = coord::fb_slice_permutation(cube);
+        let c1_index = coord::tetrad_1_permutation(cube);
+        let c2_index = coord::tetrad_2_permutation(cube);
         let perm_size = perm(4,4);
-        return e1_index + perm_size*(e2_index + perm_size*(e3_index + perm_size*(c1_index + perm_size*c2_index)));
+        e1_index + perm_size*(e2_index + perm_size*(e3_index + perm_size*(c1_index + perm_size*c2_index)))
     }
 
-    fn is_solved_g3(cube: cube::Cube) -> bool {
-        for i in 0..8 {
-            if cube.corner_permutations[i] != i as u8 { return false }
-        }
-        for i in 0..12 {
-            if cube.edge_permutations[i] != i as u8 { return false }
-        }
-        true
+    pub(crate) fn g3_definition() -> PhaseDefinition { // all cubies in place
+        PhaseDefinition::new(&Self::G3_MOVES, &[
+            coord::LR_SLICE_PERMUTATION,
+            coord::UD_SLICE_PERMUTATION,
+            coord::FB_SLICE_PERMUTATION,
+            coord::TETRAD_1_PERMUTATION,
+            coord::TETRAD_2_PERMUTATION,
+        ])
     }
 
-    pub fn solve_g3(cube: cube::Cube) -> (bool, cube::Moves) {
-        let mut prune_table = PruneTable::load_g3();
-        Self::solve_group("solve_g3".to_string(), SolveMode::Prune, cube, Some(Self::is_solved_g3), Self::get_g3_index, &Self::G3_MOVES, Some(&mut prune_table))
+    pub(crate) fn g3_phase() -> Phase {
+        Self::g3_definition().build()
     }
 
-    pub fn solve_thistlethwaite(cube: cube::Cube, name: String, print_moves: bool) -> (bool, cube::Moves) {
-        let p = profile::Profile::start(&name, print_moves);
-        let mut moves = cube::Moves(vec![]);
+    pub(crate) fn solve_g3(cube: cube::Cube, phase: &Phase, prune_table: &PruneTable) -> PhaseReport {
+        Self::solve_group("g3".to_string(), cube, phase, Some(prune_table))
+    }
 
-        let (g0_success, moves_g0) = Self::solve_g0(cube.clone());
-        let cube_g0 = cube.apply_moves(moves_g0.clone());
-        moves.extend(moves_g0.clone());
-        if print_moves {
-            println!("G0 Moves: {}", moves_g0.to_string());
-            println!("{}", cube_g0);
-        }
-        if !g0_success {
-            p.report("no solution found for G0");
-            return (false, moves);
-        }
+    pub fn phase_indices(cube: cube::Cube) -> [u32; 3] { // G1, G2 and G3 indices
+        [Self::get_g1_index(cube), Self::get_g2_index(cube), Self::get_g3_index(cube)]
+    }
 
-        let (g1_success, moves_g1) = Self::solve_g1(cube_g0.clone());
-        let cube_g1 = cube_g0.apply_moves(moves_g1.clone());
-        moves.extend(moves_g1.clone());
-        if print_moves {
-            println!("G1 Moves: {}", moves_g1.to_string());
-            println!("{}", cube_g1);
-        }
-        if !g1_success {
-            p.report("no solution found for G1");
-            return (false, moves);
-        }
+    pub fn solve_thistlethwaite(cube: cube::Cube, name: String, print_moves: bool) -> Result<SolveReport, Error> {
+        SolverContext::new().solve_thistlethwaite(cube, name, print_moves)
+    }
 
-        let (g2_success, moves_g2) = Self::solve_g2(cube_g1.clone());
-        let cube_g2 = cube_g1.apply_moves(moves_g2.clone());
-        moves.extend(moves_g2.clone());
-        if print_moves {
-            println!("G2 Moves: {}", moves_g2.to_string());
-            println!("{}", cube_g2);
-        }
-        if !g2_success {
-            p.report("no solution found for G2");
-            return (false, moves);
-        }
+    pub fn gen_prune_table_g0() -> PruneTable {
+        Self::gen_prune_table("gen_prune_table_g0".to_string(), &Self::g0_phase())
+    }
 
-        let (g3_success, moves_g3) = Self::solve_g3(cube_g2.clone());
-        let cube_g3 = cube_g2.apply_moves(moves_g3.clone());
-        moves.extend(moves_g3.clone());
-        if print_moves {
-            println!("G3 Moves: {}", moves_g3.to_string());
-            println!("{}", cube_g3);
-        }
-        if !g3_success {
-            p.report("no solution found for G3");
-            return (false, moves);
-        }
+    pub fn gen_prune_table_g1() -> PruneTable {
+        Self::gen_prune_table("gen_prune_table_g1".to_string(), &Self::g1_phase())
+    }
 
-        p.end();
-        if print_moves { println!("Full solution ({} moves): {}", moves.0.len(), moves.to_string()); }
-        return (true, moves);
+    pub fn gen_prune_table_g2() -> PruneTable {
+        Self::gen_prune_table("gen_prune_table_g2".to_string(), &Self::g2_phase())
     }
 
-    pub fn gen_prune_table_g1(cube: cube::Cube) -> PruneTable {
-        let mut table = PruneTable::new();
-        Self::solve_group("gen_prune_table_g1".to_string(), SolveMode::PruneGen, cube, None, Self::get_g1_index, &Self::G1_MOVES, Some(&mut table));
-        table
+    pub fn gen_prune_table_g3() -> PruneTable {
+        Self::gen_prune_table("gen_prune_table_g3".to_string(), &Self::g3_phase())
     }
+}
 
-    pub fn gen_prune_table_g2(cube: cube::Cube) -> PruneTable {
-        let mut table = PruneTable::new();
-        Self::solve_group("gen_prune_table_g2".to_string(), SolveMode::PruneGen, cube, None, Self::get_g2_index, &Self::G2_MOVES, Some(&mut table));
-        table
+pub(crate) struct ThistlethwaiteTables {
+    pub g0_phase: Phase,
+    pub g1_phase: Phase,
+    pub g2_phase: Phase,
+    pub g3_phase: Phase,
+    pub g0: PruneTable,
+    pub g1: PruneTable,
+    pub g2: PruneTable,
+    pub g3: PruneTable,
+}
+
+impl ThistlethwaiteTables {
+    pub fn load(dir: &Path) -> Result<Self, Error> {
+        Ok(Self { // tables first, so a missing one fails before the move tables are built
+            g0: PruneTable::load_g0(dir)?,
+            g1: PruneTable::load_g1(dir)?,
+            g2: PruneTable::load_g2(dir)?,
+            g3: PruneTable::load_g3(dir)?,
+            g0_phase: Solver::g0_phase(),
+            g1_phase: Solver::g1_phase(),
+            g2_phase: Solver::g2_phase(),
+            g3_phase: Solver::g3_phase(),
+        })
     }
+}
 
-    pub fn gen_prune_table_g3(cube: cube::Cube) -> PruneTable {
-        let mut table = PruneTable::new();
-        Self::solve_group("gen_prune_table_g3".to_string(), SolveMode::PruneGen, cube, None, Self::get_g3_index, &Self::G3_MOVES, Some(&mut table));
-        table
+type PhaseSolver = fn(cube::Cube, &Phase, &PruneTable) -> PhaseReport;
+
+impl SolverContext { // Thistlethwaite solver
+    pub fn solve_thistlethwaite(&self, cube: cube::Cube, name: String, print_moves: bool) -> Result<SolveReport, Error> {
+        Self::solve_reframed(cube, |frame, cube| {
+            let p = profile::Profile::start(&name, print_moves);
+            let tables = self.thistlethwaite_tables()?;
+            let mut report = SolveReport::new("thistlethwaite", cube);
+            let mut moves = cube::Moves(vec![]);
+            let mut current = cube;
+            let mut shown = frame;
+
+            let phases: [(&str, PhaseSolver, &Phase, &PruneTable); 4] = [
+                ("G0", Solver::solve_g0, &tables.g0_phase, &tables.g0),
+                ("G1", Solver::solve_g1, &tables.g1_phase, &tables.g1),
+                ("G2", Solver::solve_g2, &tables.g2_phase, &tables.g2),
+                ("G3", Solver::solve_g3, &tables.g3_phase, &tables.g3),
+            ];
+            for (group, solve, phase, prune_table) in phases {
+                let phase_report = solve(current, phase, prune_table);
+                current = current.apply_moves(phase_report.moves.clone());
+                moves.extend(phase_report.moves.clone());
+                if print_moves {
+                    let phase_moves = frame.reframe_moves(phase_report.moves.clone());
+                    shown = shown.apply_moves(phase_moves.clone());
+                    println!("{} Moves: {}", group, phase_moves);
+                    println!("{}", shown);
+                }
+                let success = phase_report.success;
+                report.phases.push(phase_report);
+                if !success {
+                    p.report(&format!("no solution found for {}", group));
+                    let duration = p.elapsed();
+                    return Err(Error::SearchExhausted(Box::new(report.finish(false, moves, duration))));
+                }
+            }
+
+            // the phases are solved independently, so moves at their boundaries can often be merged
+            let simplified = moves.simplify();
+            let duration = p.end();
+            if print_moves {
+                println!("Full solution ({} moves): {}", moves.0.len(), frame.reframe_moves(moves.clone()));
+                println!("Simplified solution ({} moves, {} removed): {}", simplified.0.len(), moves.0.len() - simplified.0.len(), frame.reframe_moves(simplified.clone()));
+            }
+            Ok(report.finish(true, simplified, duration))
+        })
     }
 }